@@ -7,37 +7,359 @@ export interface CosmoRequest {
     method: string;
     url: string;
     headers?: Record<string, string>;
-    body?: string;
+    /** A plain string is sent as raw text. */
+    body?: string | CosmoBody;
+    /** Optional timeout limits; unset values fall back to workspace defaults. */
+    timeouts?: CosmoTimeouts;
+    /** Id used to cancel the request via `cancel_cosmo_request`; generated when absent. */
+    request_id?: string;
+    /** Proxy for this request; overrides the workspace proxy. */
+    proxy?: CosmoProxy;
+    /** Redirect handling; follows up to 10 redirects when absent. */
+    redirect?: CosmoRedirectPolicy;
+    /** Channel for stream messages, so the caller can subscribe before sending; generated when absent. */
+    stream_channel_id?: string;
+    /** Stream the response with this framing whatever its content type. */
+    stream?: StreamFraming;
+    /** HTTP version negotiation; prefers HTTP/2 over ALPN when absent. */
+    http_version?: CosmoHttpVersion;
+    /** Return the body exactly as received, without undoing its Content-Encoding. */
+    raw_body?: boolean;
+    /** Retry transient failures; each attempt gets the full timeouts. */
+    retry?: CosmoRetryPolicy;
+    /** Environment whose variables are substituted; the workspace's active environment when absent. */
+    environment_id?: string;
+    /** Collection whose variables are substituted. */
+    collection_id?: string;
+    /**
+     * Values for `{{name}}` placeholders; override environment, collection and global variables.
+     * `\{{` sends a literal `{{`; any other placeholder without a value fails the request with a `ValidationError`.
+     */
+    variables?: Record<string, string>;
+    /**
+     * Seed for the random values of dynamic helpers, so a run can be reproduced; random when absent.
+     * Helpers: `$uuid`, `$uuidV7`, `$timestamp`, `$timestampMs` and `$isoTimestamp` with an optional
+     * offset like `-1h`, `$randomInt(min, max)`, `$randomString(len)`, `$randomEmail`, `$base64`,
+     * `$base64Decode`, `$urlEncode`, `$urlDecode`, `$sha1`/`$sha256`/`$sha512(text, encoding?)` and
+     * `$hmacSha1`/`$hmacSha256`/`$hmacSha512(key, message, encoding?)`. Quote literal arguments;
+     * bare names are variables, e.g. `{{$hmacSha256(secret, '{"id": 1}', 'base64')}}`.
+     */
+    template_seed?: number;
+}
+
+/**
+ * Retry policy; unset fields take the defaults shown.
+ * Methods that aren't idempotent (POST, PATCH) get one attempt unless `retry_non_idempotent` is set.
+ */
+export interface CosmoRetryPolicy {
+    /** Attempts in total, including the first (3). */
+    max_attempts?: number;
+    /** Delay before the first retry, doubled for each further one (200). */
+    initial_delay_ms?: number;
+    /** Upper bound for any delay, including `Retry-After` (10000). */
+    max_delay_ms?: number;
+    /** Randomize each delay between half and all of its value (true). */
+    jitter?: boolean;
+    /** ([408, 429, 502, 503, 504]) */
+    retry_on_status?: number[];
+    /** (['ConnectionRefused', 'ConnectionReset', 'TimeoutError']) */
+    retry_on_errors?: CosmoErrorType[];
+    /** (true) */
+    respect_retry_after?: boolean;
+    /** (false) */
+    retry_non_idempotent?: boolean;
+}
+
+/**
+ * One attempt made under a retry policy.
+ */
+export interface CosmoAttempt {
+    attempt: number;
+    status: number | null;
+    error_type: CosmoErrorType | null;
+    error: string | null;
+    duration_ms: number;
+    /** Wait before the next attempt; null for the last one. */
+    delay_ms: number | null;
+}
+
+/**
+ * `http2_prior_knowledge` speaks HTTP/2 without negotiating, also over plain TCP.
+ */
+export type CosmoHttpVersion = 'http1_only' | 'prefer_http2' | 'http2_prior_knowledge';
+
+/**
+ * How a streamed body is split into messages.
+ */
+export type StreamFraming = 'sse' | 'ndjson' | 'chunks';
+
+/**
+ * Redirect handling for a request.
+ */
+export interface CosmoRedirectPolicy {
+    /** When false the 3xx response itself is returned. */
+    follow?: boolean;
+    max_redirects?: number;
+    /** Keep Authorization, Cookie and Proxy-Authorization when redirected to another origin. */
+    keep_auth_across_origins?: boolean;
+}
+
+/**
+ * A redirect response followed on the way to the final response.
+ */
+export interface CosmoRedirect {
+    url: string;
+    status: number;
+    headers: CosmoHeader[];
+}
+
+/**
+ * Structured request body. The engine sets the matching Content-Type unless the
+ * request has one already; multipart bodies always use their own boundary.
+ */
+export type CosmoBody =
+    | { type: 'raw'; content: string; content_type?: string }
+    | { type: 'urlencoded'; fields: { name: string; value: string }[] }
+    | { type: 'multipart'; parts: CosmoMultipartPart[] }
+    | { type: 'file'; path: string; content_type?: string }
+    | { type: 'base64'; data: string; content_type?: string }
+    | { type: 'graphql'; query: string; variables?: Record<string, unknown>; operation_name?: string };
+
+/**
+ * One part of a multipart form; files are read from disk by path.
+ */
+export type CosmoMultipartPart =
+    | { type: 'text'; name: string; value: string; content_type?: string }
+    | { type: 'file'; name: string; path: string; filename?: string; content_type?: string };
+
+/**
+ * Outbound proxy settings. `url` may use http, https, socks5 or socks5h;
+ * `enabled: false` forces a direct connection.
+ */
+export interface CosmoProxy {
+    enabled?: boolean;
+    url: string;
+    username?: string;
+    password?: string;
+    /** Hosts reached directly: `example.com`, `.internal`, `10.0.0.0/8` or `*`. */
+    no_proxy?: string[];
+}
+
+/**
+ * Timeout limits for a request, in milliseconds.
+ */
+export interface CosmoTimeouts {
+    connect_ms?: number;
+    read_ms?: number;
+    total_ms?: number;
 }
 
+/**
+ * Failure categories reported by the engine.
+ */
+export type CosmoErrorType = 'NetworkError' | 'TimeoutError' | 'DnsError' | 'SslError' | 'InvalidUrl' | 'Cancelled' | 'ProxyError' | 'UnknownError'
+    | 'ConnectionRefused' | 'ConnectionReset' | 'CertificateInvalid' | 'CertificateExpired' | 'HostnameMismatch'
+    | 'TooManyRedirects' | 'BodyDecodeError' | 'ValidationError';
+
 /**
  * Structured error response for the UI.
  */
 export interface CosmoError {
-    error_type: 'NetworkError' | 'TimeoutError' | 'DnsError' | 'SslError' | 'InvalidUrl' | 'UnknownError' | 'DemoLimitReached';
+    error_type: CosmoErrorType | 'DemoLimitReached';
     message: string;
+    /** Underlying causes, outermost first. */
+    causes?: string[];
+    /** Attempts made under the retry policy; absent without one. */
+    attempts?: CosmoAttempt[];
+    /** The request as sent, when the failure came after variable substitution. */
+    resolved_request?: CosmoResolvedRequest;
+}
+
+/**
+ * Timing breakdown of a request, in milliseconds.
+ * Connection phases are absent when a pooled connection was reused or the phase does not apply.
+ */
+export interface CosmoTimings {
+    dns_ms?: number | null;
+    tcp_ms?: number | null;
+    tls_ms?: number | null;
+    ttfb_ms: number;
+    download_ms: number;
+    total_ms: number;
+}
+
+/**
+ * A single response header line as returned by the Rust backend.
+ * Names are lowercase, whatever casing the server sent.
+ */
+export interface CosmoHeader {
+    name: string;
+    value: string;
+    /** Exact value bytes (base64), set only when `value` is a lossy decode. */
+    value_base64?: string;
 }
 
 /**
  * Standardized response object from the Rust backend.
+ * `headers` is a lookup map for the UI; `header_list` keeps every header line, with
+ * repeated names grouped together in the order their values were received.
  */
 export interface CosmoResponse {
     status: number;
+    /** URL of the final response, after any redirects. */
+    url?: string;
+    redirects?: CosmoRedirect[];
+    /** Body decoded as text; empty for binary payloads. */
     body: string;
+    /** Byte-exact body, base64 encoded. */
+    body_base64?: string;
+    mime_type?: string;
+    charset?: string;
+    is_binary?: boolean;
     headers: Record<string, string>;
+    header_list?: CosmoHeader[];
     duration_ms: number;
+    timings?: CosmoTimings;
+    /** Whether a new connection was opened rather than reusing a pooled one. */
+    fresh_connection?: boolean;
     error?: CosmoError;
     is_stream?: boolean;
+    stream_framing?: StreamFraming;
     stream_channel_id?: string;
+    request_id?: string;
+    /** Set for GraphQL bodies when the response is a GraphQL result. */
+    graphql?: CosmoGraphQLResult | null;
+    /** e.g. `HTTP/1.1` or `HTTP/2`. */
+    http_version?: string;
+    /** e.g. `TLSv1.3`; null for plain HTTP. */
+    tls_version?: string | null;
+    /** e.g. `TLS13_AES_128_GCM_SHA256`. */
+    cipher_suite?: string | null;
+    /** Body bytes as received, before content decoding; 0 for streams. */
+    compressed_size?: number;
+    decoded_size?: number;
+    /** Status line and headers as HTTP/1.1 text (uncompressed for HTTP/2). */
+    header_size?: number;
+    /** Content codings that were undone, e.g. `gzip`. */
+    content_encoding?: string | null;
+    /** Attempts made under the retry policy; empty without one. */
+    attempts?: CosmoAttempt[];
+    /** The request as sent, with its variables substituted. */
+    resolved_request?: CosmoResolvedRequest | null;
+}
+
+/**
+ * A request after `{{variable}}` substitution.
+ */
+export interface CosmoResolvedRequest {
+    method: string;
+    url: string;
+    headers: Record<string, string>;
+    body: CosmoBody | null;
+}
+
+/**
+ * A GraphQL response with `errors` kept apart from `data`.
+ */
+export interface CosmoGraphQLResult {
+    data: unknown | null;
+    errors: CosmoGraphQLError[];
+    extensions: unknown | null;
+}
+
+export interface CosmoGraphQLError {
+    message: string;
+    locations: { line: number; column: number }[];
+    /** Field names and list indices leading to the failed field. */
+    path: (string | number)[];
+    extensions: unknown | null;
+}
+
+/**
+ * Schema summary from an introspection query, for autocompletion.
+ * Types are rendered in SDL notation, e.g. `[User!]!`.
+ */
+export interface GraphQLSchemaSummary {
+    query_type: string | null;
+    mutation_type: string | null;
+    subscription_type: string | null;
+    types: {
+        name: string;
+        kind: 'OBJECT' | 'INTERFACE' | 'UNION' | 'ENUM' | 'INPUT_OBJECT' | 'SCALAR';
+        description: string | null;
+        fields: {
+            name: string;
+            description: string | null;
+            type: string;
+            args: GraphQLInputValue[];
+            is_deprecated: boolean;
+        }[];
+        input_fields: GraphQLInputValue[];
+        enum_values: string[];
+        possible_types: string[];
+    }[];
+}
+
+export interface GraphQLInputValue {
+    name: string;
+    description: string | null;
+    type: string;
+    default_value: string | null;
+}
+
+/**
+ * Introspects the GraphQL endpoint at `request.url` using the request's headers.
+ * Rejects with a CosmoError if the request fails or the server returns no schema.
+ */
+export async function introspectGraphQLSchema(
+    request: CosmoRequest,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<GraphQLSchemaSummary> {
+    return invoke<GraphQLSchemaSummary>("introspect_graphql_schema", { request, ...workspace });
+}
+
+/**
+ * A Server-Sent Event parsed by the backend.
+ */
+export interface SseEvent {
+    /** Last event id seen on the stream. */
+    id: string | null;
+    event: string;
+    data: string;
+    retry: number | null;
+}
+
+/**
+ * Message emitted on a stream channel. A stream starts with `open` and finishes
+ * with exactly one `end` or `error`.
+ */
+export type StreamMessage =
+    | { type: 'open'; status: number; framing: StreamFraming }
+    | ({ type: 'event' } & SseEvent)
+    | { type: 'line'; line: string; json: unknown | null }
+    | { type: 'chunk'; offset: number; data_base64: string; text: string | null }
+    | { type: 'end'; closed: boolean }
+    | { type: 'error'; message: string };
+
+/**
+ * Stops reading a response stream. Resolves to false if it had already ended.
+ */
+export async function closeStream(streamChannelId: string): Promise<boolean> {
+    return invoke<boolean>("close_cosmo_stream", { streamChannelId });
 }
 
 /**
  * Bridges the UI with the Tauri Rust backend to execute an HTTP request.
  * @param request Normalized request object.
  * @param userMode User mode (authenticated or demo)
+ * @param workspace Optional workspace the request is sent from; enables workspace defaults and its cookie jar.
  * @returns A promise resolving to a CosmoResponse.
  */
-export async function executeRequest(request: CosmoRequest, userMode: 'authenticated' | 'demo' = 'authenticated'): Promise<CosmoResponse> {
+export async function executeRequest(
+    request: CosmoRequest,
+    userMode: 'authenticated' | 'demo' = 'authenticated',
+    workspace?: { userId: string; workspaceId: string }
+): Promise<CosmoResponse> {
     try {
         if (userMode === 'demo') {
             const currentCount = await invoke<number>("get_demo_request_count");
@@ -55,7 +377,12 @@ export async function executeRequest(request: CosmoRequest, userMode: 'authentic
             }
         }
 
-        const response = await invoke<CosmoResponse>("execute_cosmo_request", { request });
+        const raw = await invoke<Omit<CosmoResponse, 'headers'> & { headers: CosmoHeader[] }>("execute_cosmo_request", { request, ...workspace });
+        const response: CosmoResponse = {
+            ...raw,
+            headers: headersToRecord(raw.headers),
+            header_list: raw.headers,
+        };
 
         if (userMode === 'demo' && !response.error) {
             await invoke<number>("increment_demo_request_count");
@@ -89,3 +416,313 @@ export async function executeRequest(request: CosmoRequest, userMode: 'authentic
         };
     }
 }
+
+/**
+ * Folds an ordered header list into a lookup map, joining repeated names with ", ".
+ */
+export function headersToRecord(headers: CosmoHeader[]): Record<string, string> {
+    const record: Record<string, string> = {};
+    for (const { name, value } of headers) {
+        record[name] = name in record ? `${record[name]}, ${value}` : value;
+    }
+    return record;
+}
+
+/**
+ * Requests run together by `executeBatch`.
+ */
+export interface CosmoBatch {
+    requests: CosmoRequest[];
+    /** Requests in flight at once; 4 when absent. */
+    concurrency?: number;
+    /**
+     * Id to cancel the whole batch with via `cancel_cosmo_request`; generated when absent.
+     * Each request can also be cancelled by its own `request_id`.
+     */
+    batch_id?: string;
+    /** Channel for `BatchProgress` events, so the caller can subscribe before sending; generated when absent. */
+    progress_channel_id?: string;
+}
+
+/**
+ * Emitted on the batch's progress channel as each request completes.
+ */
+export interface BatchProgress {
+    /** Position of the request in the batch. */
+    index: number;
+    request_id: string;
+    completed: number;
+    total: number;
+    status: number | null;
+    duration_ms: number;
+    error_type: CosmoErrorType | null;
+    error: string | null;
+}
+
+/**
+ * Outcome of a batch; `items` are in input order, each with either a response or an error.
+ */
+export interface CosmoBatchResult {
+    batch_id: string;
+    progress_channel_id: string;
+    items: { index: number; request_id: string; response: CosmoResponse | null; error: CosmoError | null }[];
+    /** Requests that received a response, whatever its status. */
+    succeeded: number;
+    failed: number;
+    /** Whether any request was cancelled; finished items are kept when the batch is cancelled. */
+    cancelled: boolean;
+    duration_ms: number;
+}
+
+/**
+ * Executes many requests in the backend with bounded concurrency.
+ */
+export async function executeBatch(
+    batch: CosmoBatch,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<CosmoBatchResult> {
+    type RawResponse = Omit<CosmoResponse, 'headers'> & { headers: CosmoHeader[] };
+    const raw = await invoke<Omit<CosmoBatchResult, 'items'> & { items: (Omit<CosmoBatchResult['items'][number], 'response'> & { response: RawResponse | null })[] }>(
+        "execute_cosmo_batch", { batch, ...workspace });
+    return {
+        ...raw,
+        items: raw.items.map(item => ({
+            ...item,
+            response: item.response && { ...item.response, headers: headersToRecord(item.response.headers), header_list: item.response.headers },
+        })),
+    };
+}
+
+/**
+ * How requests are started during a load test: `rate` starts `per_second` requests every second
+ * however long they take, skipping starts while `max_in_flight` (default 1000) are still running;
+ * `concurrency` keeps `workers` requests in flight.
+ */
+export type LoadMode =
+    | { type: 'rate'; per_second: number; max_in_flight?: number }
+    | { type: 'concurrency'; workers: number };
+
+/**
+ * A load test of a single request; ends at `duration_ms` or after `count` requests, whichever comes first.
+ */
+export interface CosmoLoadTest {
+    request: CosmoRequest;
+    mode: LoadMode;
+    duration_ms?: number;
+    count?: number;
+    /** Id usable with `stopLoadTest`; generated when absent. */
+    run_id?: string;
+    /** Channel for `LoadMetrics` events, so the caller can subscribe before starting; generated when absent. */
+    metrics_channel_id?: string;
+    /** How often metrics are emitted; every second when absent. */
+    metrics_interval_ms?: number;
+}
+
+/**
+ * Live metrics emitted while a load test runs, and once at the end.
+ */
+export interface LoadMetrics {
+    elapsed_ms: number;
+    sent: number;
+    completed: number;
+    failed: number;
+    in_flight: number;
+    /** Requests finished per second since the previous metrics. */
+    throughput_rps: number;
+    /** Share of the requests finished since the previous metrics that failed. */
+    error_rate: number;
+    p50_ms: number;
+    p99_ms: number;
+}
+
+/**
+ * Final report of a load test. Latencies cover requests that received a response.
+ */
+export interface LoadReport {
+    run_id: string;
+    metrics_channel_id: string;
+    sent: number;
+    /** Requests that received a response, whatever its status. */
+    completed: number;
+    /** Requests that failed without a response. */
+    failed: number;
+    /** Whether the test was ended early with `stopLoadTest`. */
+    stopped: boolean;
+    duration_ms: number;
+    throughput_rps: number;
+    latency: { min_ms: number; mean_ms: number; max_ms: number; p50_ms: number; p90_ms: number; p95_ms: number; p99_ms: number };
+    /** Buckets that double in width from 1 ms; `up_to_ms` is inclusive. */
+    histogram: { up_to_ms: number; count: number }[];
+    /** Responses per status code. */
+    status_codes: Record<string, number>;
+    /** Most frequent first. */
+    errors: { error_type: CosmoErrorType; count: number; example: string }[];
+}
+
+/**
+ * Runs a load test and resolves with its report once it ends.
+ */
+export async function startLoadTest(
+    test: CosmoLoadTest,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<LoadReport> {
+    return invoke<LoadReport>("start_load_test", { test, ...workspace });
+}
+
+/**
+ * Stops starting new requests in a load test. Resolves to false if no such run is in progress.
+ */
+export async function stopLoadTest(runId: string): Promise<boolean> {
+    return invoke<boolean>("stop_load_test", { runId });
+}
+
+/**
+ * WebSocket connection request. Subscribe to `connection_id` before opening
+ * so no event is missed.
+ */
+export interface CosmoWebSocketRequest {
+    url: string;
+    headers?: Record<string, string>;
+    subprotocols?: string[];
+    connection_id?: string;
+}
+
+/**
+ * Details of an established WebSocket connection.
+ */
+export interface CosmoWebSocketOpened {
+    connection_id: string;
+    /** Subprotocol selected by the server. */
+    protocol: string | null;
+    status: number;
+    headers: CosmoHeader[];
+}
+
+/**
+ * Frame sent on a WebSocket connection.
+ */
+export type CosmoWebSocketFrame =
+    | { type: 'text'; data: string }
+    | { type: 'binary'; data_base64: string }
+    | { type: 'ping'; data_base64?: string };
+
+/**
+ * Event emitted on a WebSocket connection's channel. The last one is always `close` or `error`.
+ */
+export type WebSocketEvent =
+    | { type: 'text'; data: string }
+    | { type: 'binary'; data_base64: string }
+    | { type: 'ping'; data_base64: string }
+    | { type: 'pong'; data_base64: string }
+    | { type: 'close'; code: number | null; reason: string; by_client: boolean }
+    | { type: 'error'; message: string };
+
+/**
+ * Opens a WebSocket connection, applying the workspace's TLS settings when given.
+ * Rejects with a CosmoError if the handshake fails.
+ */
+export async function openWebSocket(
+    request: CosmoWebSocketRequest,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<CosmoWebSocketOpened> {
+    return invoke<CosmoWebSocketOpened>("open_websocket", { request, ...workspace });
+}
+
+/**
+ * Sends a text, binary or ping frame on an open connection.
+ */
+export async function sendWebSocketFrame(connectionId: string, frame: CosmoWebSocketFrame): Promise<void> {
+    return invoke<void>("send_websocket_message", { connectionId, frame });
+}
+
+/**
+ * Closes a connection. Resolves to false if it wasn't open.
+ */
+export async function closeWebSocket(connectionId: string, code?: number, reason?: string): Promise<boolean> {
+    return invoke<boolean>("close_websocket", { connectionId, code, reason });
+}
+
+/**
+ * Where gRPC service definitions come from: `.proto` files on disk or the server's reflection service.
+ */
+export type CosmoProtoSource =
+    | { type: 'files'; paths: string[]; include_paths?: string[] }
+    | { type: 'reflection' };
+
+/**
+ * A gRPC call. `messages` use the protobuf JSON mapping; unary and
+ * server-streaming methods take exactly one.
+ */
+export interface CosmoGrpcRequest {
+    /** e.g. `http://localhost:50051`; `https://` uses TLS. */
+    url: string;
+    /** `package.Service/Method` */
+    method: string;
+    source: CosmoProtoSource;
+    /** Keys ending in `-bin` take base64 values. */
+    metadata?: Record<string, string>;
+    messages: unknown[];
+    timeouts?: CosmoTimeouts;
+    request_id?: string;
+}
+
+export type GrpcCallKind = 'unary' | 'server_streaming' | 'client_streaming' | 'bidi_streaming';
+
+/**
+ * Result of a gRPC call. A non-OK status is reported here rather than as a CosmoError.
+ */
+export interface CosmoGrpcResponse {
+    /** 0 on success. */
+    status_code: number;
+    /** e.g. `OK`, `NOT_FOUND`. */
+    status: string;
+    status_message: string;
+    kind: GrpcCallKind;
+    messages: unknown[];
+    headers: CosmoHeader[];
+    trailers: CosmoHeader[];
+    duration_ms: number;
+    timings: {
+        connect_ms: number;
+        descriptors_ms: number;
+        ttfb_ms: number;
+        download_ms: number;
+        total_ms: number;
+    };
+    request_id: string | null;
+}
+
+export interface GrpcServiceSummary {
+    name: string;
+    methods: {
+        name: string;
+        /** Value for `CosmoGrpcRequest.method`. */
+        full_name: string;
+        kind: GrpcCallKind;
+        input_type: string;
+        output_type: string;
+        /** Input message with every field at its default. */
+        input_template: unknown;
+    }[];
+}
+
+/**
+ * Performs a gRPC call, applying the workspace's timeouts and TLS settings when given.
+ * Cancel it with `cancel_cosmo_request` and its `request_id`.
+ */
+export async function executeGrpcRequest(
+    request: CosmoGrpcRequest,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<CosmoGrpcResponse> {
+    return invoke<CosmoGrpcResponse>("execute_grpc_request", { request, ...workspace });
+}
+
+/**
+ * Lists a server's services and methods. Only `url`, `source`, `metadata` and `timeouts` are used.
+ */
+export async function describeGrpcServices(
+    request: Omit<CosmoGrpcRequest, 'method' | 'messages'>,
+    workspace?: { userId: string; workspaceId: string }
+): Promise<GrpcServiceSummary[]> {
+    return invoke<GrpcServiceSummary[]>("describe_grpc_services", { request: { method: "", messages: [], ...request }, ...workspace });
+}
@@ -3,7 +3,7 @@
 import { useState, useEffect, useRef, useCallback } from "react";
 import { listen, UnlistenFn } from "@tauri-apps/api/event";
 import { useSettings } from "./SettingsProvider";
-import { executeRequest, CosmoResponse } from "./RequestEngine";
+import { executeRequest, closeStream, CosmoResponse, StreamMessage } from "./RequestEngine";
 import { useCollections } from "./CollectionsProvider";
 import { useAuth } from "./AuthProvider";
 import UpgradeModal from "./UpgradeModal";
@@ -169,14 +169,46 @@ export default function RequestPanel({
         streamUnlistenRef.current = null;
       }
 
+      // Subscribe before sending so no stream message is missed
+      const streamChannelId = crypto.randomUUID();
+      let streamResponse: CosmoResponse | null = null;
+      let currentBody = "";
+      const unlisten = await listen<StreamMessage>(streamChannelId, (event) => {
+        const message = event.payload;
+        if (message.type === 'event') {
+          currentBody += message.data + "\n";
+        } else if (message.type === 'line') {
+          currentBody += message.line + "\n";
+        } else if (message.type === 'chunk') {
+          currentBody += message.text ?? `[${atob(message.data_base64).length} bytes]\n`;
+        } else if (message.type === 'error') {
+          currentBody += `\n[stream error] ${message.message}\n`;
+        }
+        if (streamResponse) {
+          onResponse({ ...streamResponse, body: currentBody });
+        }
+        if (message.type === 'end' || message.type === 'error') {
+          unlisten();
+          if (streamUnlistenRef.current === stopStream) {
+            streamUnlistenRef.current = null;
+          }
+        }
+      });
+      const stopStream = () => {
+        unlisten();
+        closeStream(streamChannelId);
+      };
+
       const response = await executeRequest({
         method,
         url: targetUrl,
         headers: finalHeaders,
         body: finalBody,
+        stream_channel_id: streamChannelId,
       }, isDemo ? 'demo' : 'authenticated');
 
       if (response.error && response.error.error_type === 'DemoLimitReached') {
+        unlisten();
         setShowUpgradeModal(true);
         onExecuting(false);
         return;
@@ -184,17 +216,11 @@ export default function RequestPanel({
 
       if (response.is_stream && response.stream_channel_id) {
         onExecuting(false); // Stop loader, start streaming visually
-        onResponse(response);
-
-        let currentBody = "";
-        streamUnlistenRef.current = await listen<string>(response.stream_channel_id, (event) => {
-          currentBody += event.payload;
-          onResponse({
-            ...response,
-            body: currentBody
-          });
-        });
+        streamResponse = response;
+        streamUnlistenRef.current = stopStream;
+        onResponse({ ...response, body: currentBody });
       } else {
+        unlisten();
         onResponse(response);
       }
 
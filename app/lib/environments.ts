@@ -0,0 +1,60 @@
+import { invoke } from "@tauri-apps/api/core";
+import { KVItem } from "./collections";
+
+/**
+ * A named set of variables, e.g. for dev, staging or prod.
+ */
+export interface Environment {
+    id: string;
+    name: string;
+    variables: KVItem[];
+}
+
+/**
+ * Variables of a workspace in their three scopes. Collection variables override
+ * globals, and the selected environment's variables override both.
+ */
+export interface WorkspaceEnvironments {
+    globals: KVItem[];
+    environments: Environment[];
+    /** Environment used by requests that don't name one. */
+    active_environment_id?: string | null;
+    /** Variables of each collection, by collection id. */
+    collections: Record<string, KVItem[]>;
+}
+
+/**
+ * Persists a workspace's environments beside its collections.
+ * @param userId - The current user's ID.
+ * @param workspaceId - The active workspace ID (scoping).
+ * @param environments - The environments and variables to save.
+ */
+export async function saveEnvironmentsToDisk(userId: string, workspaceId: string, environments: WorkspaceEnvironments): Promise<void> {
+    try {
+        await invoke("save_environments", {
+            userId,
+            workspaceId,
+            environments: JSON.stringify(environments)
+        });
+    } catch (error) {
+        console.error("Failed to save environments:", error);
+        throw error;
+    }
+}
+
+/**
+ * Loads a workspace's environments from the local filesystem.
+ * @param userId - The current user's ID.
+ * @param workspaceId - The active workspace ID.
+ * @returns A promise resolving to the workspace's environments, empty if none were saved.
+ */
+export async function loadEnvironmentsFromDisk(userId: string, workspaceId: string): Promise<WorkspaceEnvironments> {
+    const empty: WorkspaceEnvironments = { globals: [], environments: [], active_environment_id: null, collections: {} };
+    try {
+        const environments = await invoke<string>("load_environments", { userId, workspaceId });
+        return { ...empty, ...JSON.parse(environments) };
+    } catch (error) {
+        console.error("Failed to load environments:", error);
+        return empty;
+    }
+}
@@ -0,0 +1,218 @@
+use std::collections::HashSet;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Instant;
+
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use uuid::Uuid;
+
+use crate::inflight::InFlightRequests;
+use crate::{CosmoError, CosmoErrorType, CosmoRequest, CosmoResponse};
+
+/// Requests run together by `execute_cosmo_batch`.
+#[derive(Debug, Default, Deserialize)]
+pub struct CosmoBatch {
+    pub requests: Vec<CosmoRequest>,
+    /// Requests in flight at once; 4 when absent
+    #[serde(default)]
+    pub concurrency: Option<usize>,
+    /// Id the batch is tracked under, usable with `cancel_cosmo_request`;
+    /// generated when absent
+    #[serde(default)]
+    pub batch_id: Option<String>,
+    /// Channel to emit `BatchProgress` events on, so the caller can subscribe
+    /// before sending; generated when absent
+    #[serde(default)]
+    pub progress_channel_id: Option<String>,
+}
+
+const DEFAULT_CONCURRENCY: usize = 4;
+
+/// Emitted as each request of a batch completes, in completion order.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BatchProgress {
+    /// Position of the request in the batch
+    pub index: usize,
+    pub request_id: String,
+    /// Requests completed so far, including this one
+    pub completed: usize,
+    pub total: usize,
+    /// Response status, when a response was received
+    pub status: Option<u16>,
+    pub duration_ms: u128,
+    pub error_type: Option<CosmoErrorType>,
+    pub error: Option<String>,
+}
+
+/// Outcome of one request of a batch; exactly one of `response` and `error` is set.
+#[derive(Debug, Serialize)]
+pub struct CosmoBatchItem {
+    pub index: usize,
+    pub request_id: String,
+    pub response: Option<CosmoResponse>,
+    pub error: Option<CosmoError>,
+}
+
+/// Outcome of a whole batch.
+#[derive(Debug, Serialize)]
+pub struct CosmoBatchResult {
+    pub batch_id: String,
+    pub progress_channel_id: String,
+    /// One item per request, in input order
+    pub items: Vec<CosmoBatchItem>,
+    /// Requests that received a response, whatever its status
+    pub succeeded: usize,
+    /// Requests that failed without a response, including cancelled ones
+    pub failed: usize,
+    /// Whether any request was cancelled, on its own or with the batch
+    pub cancelled: bool,
+    pub duration_ms: u128,
+}
+
+/// Runs `requests` with at most `concurrency` in flight, calling `on_progress`
+/// as each completes. Requests without an id are given one, and each is
+/// tracked under its id in `in_flight` so it can be cancelled on its own.
+/// Requests sharing an id with another request of the batch are not sent and
+/// fail with a `ValidationError`.
+///
+/// Once `cancelled` resolves, the requests still running are cancelled and
+/// the rest are not started; they all end with a `Cancelled` error, next to
+/// the items that had already finished.
+pub async fn run_batch<P, C>(
+    app_handle: Option<tauri::AppHandle>,
+    in_flight: &InFlightRequests,
+    requests: Vec<CosmoRequest>,
+    concurrency: usize,
+    cancelled: C,
+    mut on_progress: P,
+) -> Vec<CosmoBatchItem>
+where
+    P: FnMut(BatchProgress),
+    C: Future<Output = ()>,
+{
+    let total = requests.len();
+    let mut items: Vec<Option<CosmoBatchItem>> = (0..total).map(|_| None).collect();
+    let mut requests = requests;
+    let request_ids: Vec<String> = requests
+        .iter_mut()
+        .map(|request| request.request_id.get_or_insert_with(|| Uuid::new_v4().to_string()).clone())
+        .collect();
+    let mut seen = HashSet::new();
+    let duplicates: HashSet<&str> = request_ids.iter().map(String::as_str).filter(|id| !seen.insert(*id)).collect();
+    let stopping = AtomicBool::new(false);
+
+    let mut completions = futures_util::stream::iter(requests.into_iter().enumerate())
+        .map(|(index, request)| {
+            let app_handle = app_handle.clone();
+            let request_id = request_ids[index].clone();
+            let stopping = &stopping;
+            let duplicate = duplicates.contains(request_id.as_str());
+            async move {
+                let started = Instant::now();
+                let result = if duplicate {
+                    Err(CosmoError::new(
+                        CosmoErrorType::ValidationError,
+                        format!("Request id '{}' is used by more than one request of the batch", request_id),
+                    ))
+                } else if stopping.load(Ordering::SeqCst) {
+                    Err(CosmoError::cancelled(&request_id))
+                } else {
+                    in_flight.run(&request_id, crate::do_execute_cosmo_request(app_handle, request)).await
+                };
+                (index, request_id, result, started.elapsed().as_millis())
+            }
+        })
+        .buffer_unordered(concurrency.max(1));
+    let cancelled = futures_util::FutureExt::fuse(cancelled);
+    tokio::pin!(cancelled);
+
+    let mut completed = 0;
+    loop {
+        let (index, request_id, result, duration_ms) = tokio::select! {
+            next = completions.next() => match next {
+                Some(completion) => completion,
+                None => break,
+            },
+            _ = &mut cancelled => {
+                stopping.store(true, Ordering::SeqCst);
+                for (request_id, item) in request_ids.iter().zip(&items) {
+                    if item.is_none() && !duplicates.contains(request_id.as_str()) {
+                        in_flight.cancel(request_id);
+                    }
+                }
+                continue;
+            }
+        };
+        completed += 1;
+        on_progress(BatchProgress {
+            index,
+            request_id: request_id.clone(),
+            completed,
+            total,
+            status: result.as_ref().ok().map(|response| response.status),
+            duration_ms,
+            error_type: result.as_ref().err().map(|error| error.error_type),
+            error: result.as_ref().err().map(|error| error.message.clone()),
+        });
+        let (response, error) = match result {
+            Ok(response) => (Some(response), None),
+            Err(error) => (None, Some(error)),
+        };
+        items[index] = Some(CosmoBatchItem { index, request_id, response, error });
+    }
+
+    items.into_iter().flatten().collect()
+}
+
+/// Executes many requests on the shared client pool, at most `concurrency` at
+/// a time, emitting a `BatchProgress` on the progress channel as each
+/// completes. Workspace preferences apply to every request as for
+/// `execute_cosmo_request`. Each request can be cancelled by its own id.
+/// Cancelling the batch id cancels every request not yet finished, and the
+/// batch returns with the finished ones and the rest marked `Cancelled`.
+#[tauri::command]
+pub async fn execute_cosmo_batch(
+    app_handle: tauri::AppHandle,
+    in_flight: tauri::State<'_, InFlightRequests>,
+    batch: CosmoBatch,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<CosmoBatchResult, CosmoError> {
+    let started = Instant::now();
+    let batch_id = batch.batch_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let channel_id = batch.progress_channel_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut requests = batch.requests;
+    if let Some(context) = crate::WorkspaceContext::load(&app_handle, user_id, workspace_id).await {
+        for request in &mut requests {
+            context.apply(request);
+        }
+    }
+
+    let emit = |progress: BatchProgress| {
+        if let Err(e) = app_handle.emit(&channel_id, progress) {
+            log::error!("Failed to emit batch progress on {}: {}", channel_id, e);
+        }
+    };
+    let concurrency = batch.concurrency.unwrap_or(DEFAULT_CONCURRENCY);
+    let items = in_flight
+        .run_until_cancelled(&batch_id, |cancelled| {
+            run_batch(Some(app_handle.clone()), &in_flight, requests, concurrency, cancelled, emit)
+        })
+        .await?;
+
+    let failed = items.iter().filter(|item| item.error.is_some()).count();
+    let cancelled = items
+        .iter()
+        .any(|item| item.error.as_ref().is_some_and(|error| error.error_type == CosmoErrorType::Cancelled));
+    Ok(CosmoBatchResult {
+        batch_id,
+        progress_channel_id: channel_id.clone(),
+        succeeded: items.len() - failed,
+        failed,
+        cancelled,
+        items,
+        duration_ms: started.elapsed().as_millis(),
+    })
+}
@@ -0,0 +1,104 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use encoding_rs::Encoding;
+
+/// Response body prepared for the frontend.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedBody {
+    /// Decoded text, empty for binary payloads
+    pub text: String,
+    /// Byte-exact payload, base64 encoded
+    pub base64: String,
+    /// MIME type without parameters, lowercased (e.g. `image/png`)
+    pub mime_type: Option<String>,
+    /// Charset used to decode `text`, if the body is textual
+    pub charset: Option<String>,
+    /// Whether the payload was treated as binary
+    pub is_binary: bool,
+}
+
+/// Splits a `Content-Type` header into its MIME type and `charset` parameter.
+pub fn parse_content_type(content_type: &str) -> (Option<String>, Option<String>) {
+    let mut parts = content_type.split(';');
+    let mime_type = parts
+        .next()
+        .map(|m| m.trim().to_ascii_lowercase())
+        .filter(|m| !m.is_empty());
+    let charset = parts.find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"').to_ascii_lowercase())
+        } else {
+            None
+        }
+    });
+    (mime_type, charset)
+}
+
+/// Whether a MIME type denotes human-readable text.
+pub fn is_textual_mime(mime_type: &str) -> bool {
+    let (essence, suffix) = match mime_type.split_once('+') {
+        Some((essence, suffix)) => (essence, Some(suffix)),
+        None => (mime_type, None),
+    };
+    if matches!(suffix, Some("json" | "xml" | "yaml")) {
+        return true;
+    }
+    essence.starts_with("text/")
+        || matches!(
+            essence,
+            "application/json"
+                | "application/xml"
+                | "application/javascript"
+                | "application/ecmascript"
+                | "application/x-www-form-urlencoded"
+                | "application/graphql"
+                | "application/x-ndjson"
+                | "application/yaml"
+                | "application/x-yaml"
+        )
+}
+
+/// Encodes a response payload and decodes it as text when the content type
+/// (or, without one, the bytes themselves) indicate a textual body.
+pub fn decode_body(content_type: Option<&str>, bytes: &[u8]) -> DecodedBody {
+    let (mime_type, declared_charset) = match content_type {
+        Some(ct) => parse_content_type(ct),
+        None => (None, None),
+    };
+
+    let textual = match &mime_type {
+        Some(mime) => is_textual_mime(mime) || declared_charset.is_some(),
+        None => std::str::from_utf8(bytes).is_ok(),
+    };
+
+    let (text, charset) = if textual {
+        let encoding = declared_charset
+            .as_deref()
+            .and_then(|label| Encoding::for_label(label.as_bytes()))
+            .unwrap_or(encoding_rs::UTF_8);
+        let (text, encoding, _) = encoding.decode(bytes);
+        (text.into_owned(), Some(encoding.name().to_ascii_lowercase()))
+    } else {
+        (String::new(), None)
+    };
+
+    DecodedBody {
+        text,
+        base64: STANDARD.encode(bytes),
+        mime_type,
+        charset,
+        is_binary: !textual,
+    }
+}
+
+/// Prepares a body that is still in its content encoding, which is binary
+/// whatever its content type says.
+pub fn encoded_body(content_type: Option<&str>, bytes: &[u8]) -> DecodedBody {
+    DecodedBody {
+        text: String::new(),
+        base64: STANDARD.encode(bytes),
+        mime_type: content_type.and_then(|ct| parse_content_type(ct).0),
+        charset: None,
+        is_binary: true,
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+
+use serde::{Deserialize, Serialize};
+use tauri::Manager;
+
+use crate::cookies::CookieJar;
+use crate::preferences::WorkspaceScope;
+use crate::proxy::CosmoProxy;
+use crate::timings::{ConnectTimingLayer, TimedResolver};
+use crate::tls::{CosmoTlsSettings, NegotiatedTls};
+use crate::{tls, CosmoError, CosmoErrorType};
+
+/// Settings baked into a `reqwest::Client` when it is built.
+///
+/// Requests with equal settings share one client, and with it the connection
+/// pool, HTTP keep-alive and TLS session cache.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ClientSettings {
+    pub connect_timeout_ms: Option<u64>,
+    pub read_timeout_ms: Option<u64>,
+    /// Workspace whose cookie jar the client sends and stores cookies with
+    pub cookie_scope: Option<WorkspaceScope>,
+    /// Proxy to route requests through; `None` follows the system proxy settings
+    pub proxy: Option<CosmoProxy>,
+    /// TLS settings narrowed to the request's host with `CosmoTlsSettings::for_host`
+    pub tls: CosmoTlsSettings,
+    pub http_version: CosmoHttpVersion,
+}
+
+/// How the client picks the HTTP version to speak.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CosmoHttpVersion {
+    /// Always HTTP/1.1; only `http/1.1` is offered over ALPN
+    Http1Only,
+    /// HTTP/2 when the server agrees to it over ALPN, HTTP/1.1 otherwise
+    #[default]
+    PreferHttp2,
+    /// HTTP/2 without negotiation, also over plain TCP (h2c)
+    Http2PriorKnowledge,
+}
+
+/// Builds a fresh client for the given settings, using `cookie_jar` as the
+/// cookie store when given.
+pub fn build_client(settings: &ClientSettings, cookie_jar: Option<Arc<CookieJar>>) -> Result<reqwest::Client, CosmoError> {
+    let mut tls_config = tls::client_config(&settings.tls).map_err(|e| CosmoError::new(
+        CosmoErrorType::SslError,
+        format!("Failed to initialize TLS: {}", e),
+    ))?;
+    tls_config.alpn_protocols = match settings.http_version {
+        CosmoHttpVersion::Http1Only => vec![b"http/1.1".to_vec()],
+        CosmoHttpVersion::PreferHttp2 => vec![b"h2".to_vec(), b"http/1.1".to_vec()],
+        CosmoHttpVersion::Http2PriorKnowledge => vec![b"h2".to_vec()],
+    };
+
+    let mut client_builder = reqwest::Client::builder()
+        .user_agent("Cosmonaut/1.0 (Desktop API Client)")
+        // Redirects are followed by the engine so every hop can be reported
+        .redirect(reqwest::redirect::Policy::none())
+        .use_preconfigured_tls(tls_config)
+        .dns_resolver(Arc::new(TimedResolver))
+        .connector_layer(ConnectTimingLayer);
+    match settings.http_version {
+        CosmoHttpVersion::Http1Only => client_builder = client_builder.http1_only(),
+        CosmoHttpVersion::PreferHttp2 => {}
+        CosmoHttpVersion::Http2PriorKnowledge => client_builder = client_builder.http2_prior_knowledge(),
+    }
+    if let Some(ms) = settings.connect_timeout_ms {
+        client_builder = client_builder.connect_timeout(Duration::from_millis(ms));
+    }
+    if let Some(ms) = settings.read_timeout_ms {
+        client_builder = client_builder.read_timeout(Duration::from_millis(ms));
+    }
+    match &settings.proxy {
+        Some(proxy) if proxy.enabled => client_builder = client_builder.proxy(proxy.to_reqwest()?),
+        Some(_) => client_builder = client_builder.no_proxy(),
+        None => {}
+    }
+    if let Some(jar) = cookie_jar {
+        client_builder = client_builder.cookie_provider(jar);
+    }
+
+    client_builder.build().map_err(|e| CosmoError::new(
+        CosmoErrorType::UnknownError,
+        format!("Failed to initialize HTTP client: {}", e),
+    ))
+}
+
+/// A pooled client, along with what its TLS handshakes negotiated.
+#[derive(Clone)]
+pub struct PooledClient {
+    pub client: reqwest::Client,
+    /// Last handshake per origin, so responses on connections this client
+    /// reuses can still report it
+    negotiated: Arc<Mutex<HashMap<String, NegotiatedTls>>>,
+}
+
+impl PooledClient {
+    fn new(client: reqwest::Client) -> PooledClient {
+        PooledClient { client, negotiated: Arc::default() }
+    }
+
+    /// Records what this client's connection to `url`'s origin negotiated.
+    pub fn remember_negotiated(&self, url: &reqwest::Url, negotiated: NegotiatedTls) {
+        self.negotiated.lock().unwrap().insert(url.origin().ascii_serialization(), negotiated);
+    }
+
+    /// Returns what this client's last connection to `url`'s origin
+    /// negotiated, if it used TLS.
+    pub fn negotiated_for(&self, url: &reqwest::Url) -> Option<NegotiatedTls> {
+        self.negotiated.lock().unwrap().get(&url.origin().ascii_serialization()).cloned()
+    }
+}
+
+/// Most clients kept at once; the least recently used one makes room.
+const MAX_POOLED_CLIENTS: usize = 32;
+/// Clients unused for this long are dropped, closing their idle connections.
+const CLIENT_IDLE_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+struct PoolEntry {
+    client: PooledClient,
+    /// Modification times of the TLS files the client was built from
+    file_stamps: Vec<Option<SystemTime>>,
+    last_used: Instant,
+}
+
+/// Modification times of the TLS files `settings` reads; `None` for files
+/// that can't be read. Looked up on a blocking thread, so a slow disk doesn't
+/// stall other requests.
+async fn file_stamps(settings: &ClientSettings) -> Vec<Option<SystemTime>> {
+    let files: Vec<String> = settings.tls.files().into_iter().map(str::to_string).collect();
+    if files.is_empty() {
+        return Vec::new();
+    }
+    let stamp = move || {
+        files
+            .into_iter()
+            .map(|path| std::fs::metadata(path).and_then(|metadata| metadata.modified()).ok())
+            .collect()
+    };
+    tokio::task::spawn_blocking(stamp).await.unwrap_or_default()
+}
+
+/// Shared HTTP clients keyed by their settings, managed as Tauri state.
+///
+/// Clients are rebuilt when a CA or client certificate file they were built
+/// from changes, and dropped once idle or crowded out by newer ones.
+pub struct ClientPool {
+    clients: Mutex<HashMap<ClientSettings, PoolEntry>>,
+    max_clients: usize,
+    idle_timeout: Duration,
+}
+
+impl Default for ClientPool {
+    fn default() -> ClientPool {
+        ClientPool {
+            clients: Mutex::default(),
+            max_clients: MAX_POOLED_CLIENTS,
+            idle_timeout: CLIENT_IDLE_TIMEOUT,
+        }
+    }
+}
+
+impl ClientPool {
+    /// Creates a pool with its own limits, so tests can reach them quickly.
+    #[cfg(test)]
+    pub fn with_limits(max_clients: usize, idle_timeout: Duration) -> ClientPool {
+        ClientPool { max_clients, idle_timeout, ..ClientPool::default() }
+    }
+
+    /// Returns the pooled client for `settings`, building it on first use.
+    /// `cookie_jar` must be the jar of `settings.cookie_scope`.
+    ///
+    /// Clients are built on a blocking thread, without holding the pool's
+    /// lock, since that reads CA and identity files.
+    pub async fn client(&self, settings: &ClientSettings, cookie_jar: Option<Arc<CookieJar>>) -> Result<PooledClient, CosmoError> {
+        let stamps = file_stamps(settings).await;
+        if let Some(client) = self.pooled(settings, &stamps) {
+            return Ok(client);
+        }
+
+        let build = {
+            let settings = settings.clone();
+            move || build_client(&settings, cookie_jar)
+        };
+        let client = tokio::task::spawn_blocking(build).await.map_err(|e| CosmoError::new(
+            CosmoErrorType::UnknownError,
+            format!("Failed to initialize HTTP client: {}", e),
+        ))??;
+
+        // Another request may have built the same client meanwhile
+        if let Some(client) = self.pooled(settings, &stamps) {
+            return Ok(client);
+        }
+        let now = Instant::now();
+        let client = PooledClient::new(client);
+        let mut clients = self.clients.lock().unwrap();
+        if !clients.contains_key(settings) && clients.len() >= self.max_clients {
+            let oldest = clients.iter().min_by_key(|(_, entry)| entry.last_used).map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                clients.remove(&oldest);
+            }
+        }
+        clients.insert(settings.clone(), PoolEntry { client: client.clone(), file_stamps: stamps, last_used: now });
+        Ok(client)
+    }
+
+    /// Returns the client for `settings` if it is pooled and was built from
+    /// files with these `stamps`, dropping idle clients on the way.
+    fn pooled(&self, settings: &ClientSettings, stamps: &[Option<SystemTime>]) -> Option<PooledClient> {
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap();
+        clients.retain(|_, entry| now.duration_since(entry.last_used) < self.idle_timeout);
+        let entry = clients.get_mut(settings).filter(|entry| entry.file_stamps == stamps)?;
+        entry.last_used = now;
+        Some(entry.client.clone())
+    }
+}
+
+/// Picks the clients one request is sent with. Client certificates are
+/// presented per host, so every host the request is redirected to gets a
+/// client built for it instead of reusing the first URL's.
+#[derive(Clone)]
+pub struct RequestClients {
+    app_handle: Option<tauri::AppHandle>,
+    /// Settings of every hop, with the TLS settings not yet narrowed to a host
+    settings: ClientSettings,
+    cookie_jar: Option<Arc<CookieJar>>,
+    /// Clients of this request alone, used when there is no app pool
+    local: Arc<ClientPool>,
+}
+
+impl RequestClients {
+    pub fn new(app_handle: Option<tauri::AppHandle>, settings: ClientSettings, cookie_jar: Option<Arc<CookieJar>>) -> RequestClients {
+        RequestClients { app_handle, settings, cookie_jar, local: Arc::default() }
+    }
+
+    /// Returns the client for sending to `url`, from the app's pool when running
+    /// with an app handle.
+    pub async fn for_url(&self, url: &reqwest::Url) -> Result<PooledClient, CosmoError> {
+        let settings = ClientSettings {
+            tls: self.settings.tls.for_host(url.host_str().unwrap_or("")),
+            ..self.settings.clone()
+        };
+        match self.app_handle.as_ref().and_then(|h| h.try_state::<ClientPool>()) {
+            Some(pool) => pool.client(&settings, self.cookie_jar.clone()).await,
+            None => self.local.client(&settings, self.cookie_jar.clone()).await,
+        }
+    }
+}
@@ -0,0 +1,300 @@
+use std::io::{self, Write};
+
+/// `Accept-Encoding` sent when the request doesn't set its own.
+pub const ACCEPT_ENCODING: &str = "gzip, deflate, br, zstd";
+
+/// Most bytes a body decodes to, or a single streamed chunk expands to,
+/// before decoding fails instead of filling memory.
+pub const MAX_DECODED_SIZE: usize = 256 * 1024 * 1024;
+
+/// Content codings the engine can undo.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ContentCoding {
+    Gzip,
+    /// zlib-wrapped deflate, as RFC 9110 defines `deflate`
+    Deflate,
+    Brotli,
+    Zstd,
+}
+
+impl ContentCoding {
+    fn parse(token: &str) -> Option<ContentCoding> {
+        match token.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(ContentCoding::Gzip),
+            "deflate" => Some(ContentCoding::Deflate),
+            "br" => Some(ContentCoding::Brotli),
+            "zstd" => Some(ContentCoding::Zstd),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            ContentCoding::Gzip => "gzip",
+            ContentCoding::Deflate => "deflate",
+            ContentCoding::Brotli => "br",
+            ContentCoding::Zstd => "zstd",
+        }
+    }
+}
+
+/// Output of a decoding step, refusing to hold more than `limit` bytes.
+struct Output {
+    buffer: Vec<u8>,
+    limit: usize,
+}
+
+impl Write for Output {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.buffer.len() + data.len() > self.limit {
+            return Err(io::Error::other(format!("decoded body is larger than {} bytes", self.limit)));
+        }
+        self.buffer.extend_from_slice(data);
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// One decoding step, writing its output to an in-memory buffer.
+enum Stage {
+    Gzip(flate2::write::MultiGzDecoder<Output>),
+    Deflate(flate2::write::ZlibDecoder<Output>),
+    Brotli(Box<brotli::DecompressorWriter<Output>>),
+    /// The low-level writer, since only its `finish` notices a cut-off frame
+    Zstd(zstd::stream::zio::Writer<Output, zstd::stream::raw::Decoder<'static>>),
+}
+
+impl Stage {
+    fn new(coding: ContentCoding, limit: usize) -> io::Result<Stage> {
+        let output = Output { buffer: Vec::new(), limit };
+        Ok(match coding {
+            ContentCoding::Gzip => Stage::Gzip(flate2::write::MultiGzDecoder::new(output)),
+            ContentCoding::Deflate => Stage::Deflate(flate2::write::ZlibDecoder::new(output)),
+            ContentCoding::Brotli => Stage::Brotli(Box::new(brotli::DecompressorWriter::new(output, 8192))),
+            ContentCoding::Zstd => Stage::Zstd(zstd::stream::zio::Writer::new(output, zstd::stream::raw::Decoder::new()?)),
+        })
+    }
+
+    fn writer(&mut self) -> &mut dyn Write {
+        match self {
+            Stage::Gzip(decoder) => decoder,
+            Stage::Deflate(decoder) => decoder,
+            Stage::Brotli(decoder) => decoder.as_mut(),
+            Stage::Zstd(decoder) => decoder,
+        }
+    }
+
+    /// Decodes `input`, returning whatever output it completed.
+    fn feed(&mut self, input: &[u8]) -> io::Result<Vec<u8>> {
+        let writer = self.writer();
+        writer.write_all(input)?;
+        writer.flush()?;
+        Ok(self.take_output())
+    }
+
+    /// Checks the stream ended properly and returns the remaining output.
+    fn finish(&mut self) -> io::Result<Vec<u8>> {
+        match self {
+            Stage::Gzip(decoder) => decoder.try_finish()?,
+            Stage::Deflate(decoder) => decoder.try_finish()?,
+            Stage::Brotli(decoder) => decoder.close()?,
+            Stage::Zstd(decoder) => decoder.finish()?,
+        }
+        Ok(self.take_output())
+    }
+
+    fn take_output(&mut self) -> Vec<u8> {
+        let output = match self {
+            Stage::Gzip(decoder) => decoder.get_mut(),
+            Stage::Deflate(decoder) => decoder.get_mut(),
+            Stage::Brotli(decoder) => decoder.get_mut(),
+            Stage::Zstd(decoder) => decoder.writer_mut(),
+        };
+        std::mem::take(&mut output.buffer)
+    }
+}
+
+/// Incrementally undoes a response's `Content-Encoding`, so streamed bodies
+/// can be decoded chunk by chunk as well as buffered ones.
+pub struct BodyDecoder {
+    /// Stages in decoding order, i.e. the reverse of the header's order
+    stages: Vec<Stage>,
+    codings: Vec<ContentCoding>,
+    limit: usize,
+}
+
+impl BodyDecoder {
+    /// Builds a decoder for a `Content-Encoding` header value. Returns `None`
+    /// when there is nothing to undo, or when one of the codings isn't
+    /// supported and the body has to be passed through as received.
+    pub fn for_content_encoding(value: Option<&str>) -> Option<BodyDecoder> {
+        let mut codings = Vec::new();
+        for token in value?.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+            if token.eq_ignore_ascii_case("identity") {
+                continue;
+            }
+            codings.push(ContentCoding::parse(token)?);
+        }
+        if codings.is_empty() {
+            return None;
+        }
+
+        BodyDecoder::with_limit(codings, MAX_DECODED_SIZE).ok()
+    }
+
+    fn with_limit(codings: Vec<ContentCoding>, limit: usize) -> io::Result<BodyDecoder> {
+        let stages = codings.iter().rev().map(|&coding| Stage::new(coding, limit)).collect::<io::Result<_>>()?;
+        Ok(BodyDecoder { stages, codings, limit })
+    }
+
+    /// Lowers the size limit, so tests don't need to decode hundreds of megabytes.
+    #[cfg(test)]
+    fn limited_to(self, limit: usize) -> BodyDecoder {
+        BodyDecoder::with_limit(self.codings, limit).expect("decoders built before")
+    }
+
+    /// The codings being undone, as they appeared in the header, e.g. `gzip`.
+    pub fn content_encoding(&self) -> String {
+        self.codings.iter().map(|coding| coding.name()).collect::<Vec<_>>().join(", ")
+    }
+
+    /// Decodes the next chunk of the body.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<u8>, String> {
+        self.run(chunk, false)
+    }
+
+    /// Flushes the decoders once the body has ended, failing if it was cut short.
+    pub fn finish(&mut self) -> Result<Vec<u8>, String> {
+        self.run(&[], true)
+    }
+
+    /// Decodes a complete body, failing if it decodes to more than
+    /// `MAX_DECODED_SIZE` bytes. An empty body, as sent with `204` or `304`,
+    /// stays empty.
+    pub fn decode(mut self, body: &[u8]) -> Result<Vec<u8>, String> {
+        if body.is_empty() {
+            return Ok(Vec::new());
+        }
+        let mut decoded = self.feed(body)?;
+        decoded.extend(self.finish()?);
+        if decoded.len() > self.limit {
+            return Err(format!(
+                "Failed to decode {} response body: decoded body is larger than {} bytes",
+                self.content_encoding(),
+                self.limit,
+            ));
+        }
+        Ok(decoded)
+    }
+
+    fn run(&mut self, input: &[u8], last: bool) -> Result<Vec<u8>, String> {
+        let content_encoding = self.content_encoding();
+        let mut data = input.to_vec();
+        for stage in &mut self.stages {
+            let mut output = stage.feed(&data);
+            if last {
+                output = output.and_then(|mut output| {
+                    output.extend(stage.finish()?);
+                    Ok(output)
+                });
+            }
+            data = output.map_err(|e| format!("Failed to decode {} response body: {}", content_encoding, e))?;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ORIGINAL: &str = "hello hello hello hello hello hello hello hello";
+
+    fn gzip(input: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        encoder.write_all(input).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    fn brotli(input: &[u8]) -> Vec<u8> {
+        let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+        encoder.write_all(input).unwrap();
+        encoder.into_inner()
+    }
+
+    fn decoder(content_encoding: &str) -> BodyDecoder {
+        BodyDecoder::for_content_encoding(Some(content_encoding)).unwrap()
+    }
+
+    #[test]
+    fn test_content_codings() {
+        let deflate = {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(ORIGINAL.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let cases = [
+            ("gzip", gzip(ORIGINAL.as_bytes())),
+            ("X-Gzip", gzip(ORIGINAL.as_bytes())),
+            ("deflate", deflate),
+            ("br", brotli(ORIGINAL.as_bytes())),
+            ("zstd", zstd::encode_all(ORIGINAL.as_bytes(), 3).unwrap()),
+            // Applied in header order: gzip first, then brotli
+            ("gzip, identity, br", brotli(&gzip(ORIGINAL.as_bytes()))),
+        ];
+        for (encoding, body) in cases {
+            assert_eq!(decoder(encoding).decode(&body).unwrap(), ORIGINAL.as_bytes(), "{}", encoding);
+        }
+        assert_eq!(decoder("gzip, identity, br").content_encoding(), "gzip, br");
+
+        assert!(BodyDecoder::for_content_encoding(None).is_none());
+        assert!(BodyDecoder::for_content_encoding(Some("identity")).is_none());
+        assert!(BodyDecoder::for_content_encoding(Some("gzip, compress")).is_none());
+
+        let error = decoder("gzip").decode(b"not gzip").unwrap_err();
+        assert!(error.starts_with("Failed to decode gzip response body"), "{}", error);
+        // An empty body, as sent with 204 or 304, stays empty
+        assert!(decoder("zstd").decode(b"").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_streamed_and_truncated_bodies() {
+        // Streamed bodies are decoded as they arrive
+        let gzipped = gzip(ORIGINAL.as_bytes());
+        let mut streaming = decoder("gzip");
+        let mut decoded = Vec::new();
+        for byte in &gzipped {
+            decoded.extend(streaming.feed(&[*byte]).unwrap());
+        }
+        decoded.extend(streaming.finish().unwrap());
+        assert_eq!(decoded, ORIGINAL.as_bytes());
+
+        // Cut-off frames fail for every coding, zstd included
+        let zstd = zstd::encode_all(ORIGINAL.as_bytes(), 3).unwrap();
+        let error = decoder("zstd").decode(&zstd[..zstd.len() - 4]).unwrap_err();
+        assert!(error.contains("zstd"), "{}", error);
+        let error = decoder("gzip").decode(&gzipped[..gzipped.len() - 4]).unwrap_err();
+        assert!(error.contains("gzip"), "{}", error);
+    }
+
+    #[test]
+    fn test_decoded_size_limit() {
+        // Bodies that expand past the limit fail instead of filling memory
+        let bomb = zstd::encode_all(&vec![0u8; 1 << 20][..], 19).unwrap();
+        assert!(bomb.len() < 1024);
+        for chunked in [false, true] {
+            let mut limited = decoder("zstd").limited_to(64 * 1024);
+            let error = if chunked {
+                limited.feed(&bomb).unwrap_err()
+            } else {
+                limited.decode(&bomb).unwrap_err()
+            };
+            assert!(error.contains("larger than 65536 bytes"), "{}", error);
+        }
+        let exact = decoder("zstd").limited_to(1 << 20).decode(&bomb).unwrap();
+        assert_eq!(exact.len(), 1 << 20);
+    }
+}
@@ -0,0 +1,340 @@
+use cookie_store::{CookieDomain, CookieExpiration, CookieStore};
+use reqwest::header::HeaderValue;
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+
+use crate::preferences::WorkspaceScope;
+
+/// A cookie as exposed to the frontend for listing and editing.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmoCookie {
+    pub name: String,
+    pub value: String,
+    /// Domain the cookie is sent to, without a leading dot
+    pub domain: String,
+    #[serde(default = "default_cookie_path")]
+    pub path: String,
+    /// Sent only to `domain` itself rather than to its subdomains as well
+    #[serde(default)]
+    pub host_only: bool,
+    #[serde(default)]
+    pub secure: bool,
+    #[serde(default)]
+    pub http_only: bool,
+    /// Expiry as RFC 3339; `None` for session cookies
+    #[serde(default)]
+    pub expires: Option<String>,
+}
+
+fn default_cookie_path() -> String {
+    "/".to_string()
+}
+
+impl CosmoCookie {
+    fn from_stored(cookie: &cookie_store::Cookie<'static>) -> CosmoCookie {
+        let (domain, host_only) = match &cookie.domain {
+            CookieDomain::HostOnly(domain) => (domain.clone(), true),
+            CookieDomain::Suffix(domain) => (domain.clone(), false),
+            CookieDomain::NotPresent | CookieDomain::Empty => (String::new(), true),
+        };
+        let expires = match &cookie.expires {
+            CookieExpiration::AtUtc(at) => chrono::DateTime::from_timestamp(at.unix_timestamp(), 0)
+                .map(|at| at.to_rfc3339()),
+            CookieExpiration::SessionEnd => None,
+        };
+
+        CosmoCookie {
+            name: cookie.name().to_string(),
+            value: cookie.value().to_string(),
+            domain,
+            path: String::from(&cookie.path),
+            host_only,
+            secure: cookie.secure().unwrap_or(false),
+            http_only: cookie.http_only().unwrap_or(false),
+            expires,
+        }
+    }
+}
+
+/// Cookie jar for one workspace, persisted as `cookies.json` next to
+/// `collections.json` and shared by every request sent from the workspace.
+#[derive(Debug)]
+pub struct CookieJar {
+    store: Arc<RwLock<CookieStore>>,
+    /// Where the jar is saved; `None` keeps it in memory only
+    file: Option<Arc<JarFile>>,
+}
+
+/// The file a jar is saved to.
+#[derive(Debug)]
+struct JarFile {
+    path: PathBuf,
+    /// Held while writing, so concurrent saves don't interleave
+    writing: Mutex<()>,
+    /// Whether a background save is queued but hasn't taken its snapshot yet
+    queued: AtomicBool,
+}
+
+impl JarFile {
+    /// Writes the cookies currently in `store`, including session cookies so
+    /// that a login survives restarting the app.
+    fn write(&self, store: &RwLock<CookieStore>) -> Result<(), String> {
+        let _writing = self.writing.lock().unwrap();
+        self.queued.store(false, Ordering::SeqCst);
+        let content = {
+            let store = store.read().unwrap();
+            let cookies: Vec<&cookie_store::Cookie<'static>> = store.iter_unexpired().collect();
+            serde_json::to_string_pretty(&cookies).map_err(|e| e.to_string())?
+        };
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+        }
+        std::fs::write(&self.path, content).map_err(|e| e.to_string())
+    }
+}
+
+impl CookieJar {
+    /// Creates an empty jar that is never written to disk.
+    #[cfg(test)]
+    pub fn in_memory() -> CookieJar {
+        CookieJar {
+            store: Arc::default(),
+            file: None,
+        }
+    }
+
+    /// Loads the jar saved at `path`, starting empty if there is none yet.
+    /// Cookies that expired while the app was closed are dropped.
+    pub fn load(path: PathBuf) -> CookieJar {
+        let store = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| {
+                serde_json::from_str::<Vec<cookie_store::Cookie<'static>>>(&content)
+                    .map_err(|e| log::warn!("Ignoring malformed cookie jar {}: {}", path.display(), e))
+                    .ok()
+            })
+            .and_then(|cookies| CookieStore::from_cookies(cookies.into_iter().map(Ok::<_, ()>), false).ok())
+            .unwrap_or_default();
+
+        CookieJar {
+            store: Arc::new(RwLock::new(store)),
+            file: Some(Arc::new(JarFile { path, writing: Mutex::new(()), queued: AtomicBool::new(false) })),
+        }
+    }
+
+    /// Lists unexpired cookies, optionally only those for `domain`.
+    pub fn list(&self, domain: Option<&str>) -> Vec<CosmoCookie> {
+        self.store
+            .read()
+            .unwrap()
+            .iter_unexpired()
+            .map(CosmoCookie::from_stored)
+            .filter(|c| domain.map_or(true, |d| c.domain.eq_ignore_ascii_case(d.trim_start_matches('.'))))
+            .collect()
+    }
+
+    /// Inserts a cookie, replacing any existing one with the same domain, path and name.
+    pub fn upsert(&self, cookie: &CosmoCookie) -> Result<(), String> {
+        let domain = cookie.domain.trim_start_matches('.');
+        if domain.is_empty() || cookie.name.is_empty() {
+            return Err("Cookie name and domain are required".to_string());
+        }
+
+        let scheme = if cookie.secure { "https" } else { "http" };
+        let url = Url::parse(&format!("{}://{}{}", scheme, domain, cookie.path))
+            .map_err(|e| format!("Invalid cookie domain or path: {}", e))?;
+
+        let mut raw = cookie::Cookie::build((cookie.name.clone(), cookie.value.clone()))
+            .path(cookie.path.clone())
+            .secure(cookie.secure)
+            .http_only(cookie.http_only);
+        if !cookie.host_only {
+            raw = raw.domain(domain.to_string());
+        }
+        if let Some(expires) = &cookie.expires {
+            let at = chrono::DateTime::parse_from_rfc3339(expires)
+                .map_err(|e| format!("Invalid cookie expiry '{}': {}", expires, e))?;
+            let at = cookie::time::OffsetDateTime::from_unix_timestamp(at.timestamp())
+                .map_err(|e| format!("Invalid cookie expiry '{}': {}", expires, e))?;
+            raw = raw.expires(at);
+        }
+
+        self.store
+            .write()
+            .unwrap()
+            .insert_raw(&raw.build(), &url)
+            .map_err(|e| format!("Failed to store cookie: {:?}", e))?;
+        self.save()
+    }
+
+    /// Removes a single cookie. Returns whether it existed.
+    pub fn remove(&self, domain: &str, path: &str, name: &str) -> Result<bool, String> {
+        let removed = self
+            .store
+            .write()
+            .unwrap()
+            .remove(domain.trim_start_matches('.'), path, name)
+            .is_some();
+        self.save()?;
+        Ok(removed)
+    }
+
+    /// Removes every cookie, or only those for `domain`.
+    pub fn clear(&self, domain: Option<&str>) -> Result<(), String> {
+        {
+            let mut store = self.store.write().unwrap();
+            match domain {
+                Some(domain) => {
+                    let doomed: Vec<(String, String, String)> = store
+                        .iter_any()
+                        .map(CosmoCookie::from_stored)
+                        .filter(|c| c.domain.eq_ignore_ascii_case(domain.trim_start_matches('.')))
+                        .map(|c| (c.domain, c.path, c.name))
+                        .collect();
+                    for (domain, path, name) in doomed {
+                        store.remove(&domain, &path, &name);
+                    }
+                }
+                None => store.clear(),
+            }
+        }
+        self.save()
+    }
+
+    /// Writes the jar to disk.
+    fn save(&self) -> Result<(), String> {
+        match &self.file {
+            Some(file) => file.write(&self.store),
+            None => Ok(()),
+        }
+    }
+
+    /// Writes the jar to disk on a blocking thread, off the request path.
+    /// Changes made while a save is queued are picked up by that save.
+    fn save_in_background(&self) {
+        let Some(file) = &self.file else { return };
+        if file.queued.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        let (file, store) = (file.clone(), self.store.clone());
+        let save = move || {
+            if let Err(e) = file.write(&store) {
+                log::error!("Failed to persist cookies: {}", e);
+            }
+        };
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => drop(runtime.spawn_blocking(save)),
+            Err(_) => save(),
+        }
+    }
+}
+
+impl reqwest::cookie::CookieStore for CookieJar {
+    fn set_cookies(&self, cookie_headers: &mut dyn Iterator<Item = &HeaderValue>, url: &Url) {
+        let cookies = cookie_headers
+            .filter_map(|value| std::str::from_utf8(value.as_bytes()).ok())
+            .filter_map(|value| cookie::Cookie::parse(value.to_string()).ok());
+        self.store.write().unwrap().store_response_cookies(cookies, url);
+        self.save_in_background();
+    }
+
+    fn cookies(&self, url: &Url) -> Option<HeaderValue> {
+        let header = self
+            .store
+            .read()
+            .unwrap()
+            .get_request_values(url)
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect::<Vec<_>>()
+            .join("; ");
+
+        if header.is_empty() {
+            None
+        } else {
+            HeaderValue::from_str(&header).ok()
+        }
+    }
+}
+
+/// Open cookie jars keyed by workspace, managed as Tauri state.
+#[derive(Default)]
+pub struct CookieJars {
+    jars: Mutex<HashMap<WorkspaceScope, Arc<CookieJar>>>,
+}
+
+impl CookieJars {
+    /// Returns the jar for `scope`, loading it from disk on first use. The
+    /// file is read on a blocking thread without holding the lock, so other
+    /// workspaces' jars stay available meanwhile.
+    pub async fn jar(&self, app_handle: &tauri::AppHandle, scope: &WorkspaceScope) -> Result<Arc<CookieJar>, String> {
+        if let Some(jar) = self.jars.lock().unwrap().get(scope) {
+            return Ok(jar.clone());
+        }
+
+        let path = scope.dir(app_handle)?.join("cookies.json");
+        let jar = tokio::task::spawn_blocking(move || CookieJar::load(path))
+            .await
+            .map_err(|e| format!("Failed to load cookies: {}", e))?;
+        // Another caller may have loaded the jar meanwhile; keep the first
+        Ok(self.jars.lock().unwrap().entry(scope.clone()).or_insert_with(|| Arc::new(jar)).clone())
+    }
+}
+
+/// Lists the cookies stored for a workspace, optionally filtered by domain.
+#[tauri::command]
+pub async fn list_cookies(
+    app_handle: tauri::AppHandle,
+    jars: tauri::State<'_, CookieJars>,
+    user_id: String,
+    workspace_id: String,
+    domain: Option<String>,
+) -> Result<Vec<CosmoCookie>, String> {
+    let jar = jars.jar(&app_handle, &WorkspaceScope::new(user_id, workspace_id)).await?;
+    Ok(jar.list(domain.as_deref()))
+}
+
+/// Creates or replaces a cookie in a workspace's jar.
+#[tauri::command]
+pub async fn save_cookie(
+    app_handle: tauri::AppHandle,
+    jars: tauri::State<'_, CookieJars>,
+    user_id: String,
+    workspace_id: String,
+    cookie: CosmoCookie,
+) -> Result<(), String> {
+    let jar = jars.jar(&app_handle, &WorkspaceScope::new(user_id, workspace_id)).await?;
+    jar.upsert(&cookie)
+}
+
+/// Deletes a single cookie from a workspace's jar.
+#[tauri::command]
+pub async fn delete_cookie(
+    app_handle: tauri::AppHandle,
+    jars: tauri::State<'_, CookieJars>,
+    user_id: String,
+    workspace_id: String,
+    domain: String,
+    path: String,
+    name: String,
+) -> Result<bool, String> {
+    let jar = jars.jar(&app_handle, &WorkspaceScope::new(user_id, workspace_id)).await?;
+    jar.remove(&domain, &path, &name)
+}
+
+/// Clears a workspace's cookies, either all of them or those for one domain.
+#[tauri::command]
+pub async fn clear_cookies(
+    app_handle: tauri::AppHandle,
+    jars: tauri::State<'_, CookieJars>,
+    user_id: String,
+    workspace_id: String,
+    domain: Option<String>,
+) -> Result<(), String> {
+    let jar = jars.jar(&app_handle, &WorkspaceScope::new(user_id, workspace_id)).await?;
+    jar.clear(domain.as_deref())
+}
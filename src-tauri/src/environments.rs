@@ -0,0 +1,228 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::preferences::WorkspaceScope;
+use crate::request_body::CosmoBody;
+use crate::template::{self, DynamicValues, HelperArg, HelperCall};
+use crate::{CosmoError, CosmoErrorType, CosmoRequest};
+
+/// A named value substituted for `{{name}}` placeholders.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmoVariable {
+    pub key: String,
+    pub value: String,
+    /// Disabled variables are kept but not substituted
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// A named set of variables, e.g. for dev, staging or prod.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoEnvironment {
+    pub id: String,
+    pub name: String,
+    pub variables: Vec<CosmoVariable>,
+}
+
+/// Variables of a workspace in their three scopes.
+///
+/// Persisted as `environments.json` next to `collections.json`. A collection's
+/// variables override the globals, and the selected environment's override both.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspaceEnvironments {
+    /// Variables available to every request of the workspace
+    pub globals: Vec<CosmoVariable>,
+    pub environments: Vec<CosmoEnvironment>,
+    /// Environment used by requests that don't name one
+    pub active_environment_id: Option<String>,
+    /// Variables of each collection, by collection id
+    pub collections: HashMap<String, Vec<CosmoVariable>>,
+}
+
+impl WorkspaceEnvironments {
+    /// Merges the scopes that apply to a request. `environment_id` falls back
+    /// to the active environment.
+    pub fn variables(&self, environment_id: Option<&str>, collection_id: Option<&str>) -> HashMap<String, String> {
+        let environment_id = environment_id.or(self.active_environment_id.as_deref());
+        let environment = environment_id.and_then(|id| self.environments.iter().find(|env| env.id == id));
+        let collection = collection_id.and_then(|id| self.collections.get(id));
+
+        let mut variables = HashMap::new();
+        let scopes = [Some(&self.globals), collection, environment.map(|env| &env.variables)];
+        for variable in scopes.into_iter().flatten().flatten().filter(|variable| variable.enabled) {
+            variables.insert(variable.key.clone(), variable.value.clone());
+        }
+        variables
+    }
+}
+
+/// Loads a workspace's environments, falling back to none when the file is
+/// missing or cannot be parsed.
+pub fn load_environments(app_handle: &tauri::AppHandle, scope: &WorkspaceScope) -> WorkspaceEnvironments {
+    let file_path = match scope.dir(app_handle) {
+        Ok(dir) => dir.join("environments.json"),
+        Err(e) => {
+            log::warn!("Failed to resolve workspace directory: {}", e);
+            return WorkspaceEnvironments::default();
+        }
+    };
+
+    match std::fs::read_to_string(&file_path) {
+        Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+            log::warn!("Ignoring malformed workspace environments: {}", e);
+            WorkspaceEnvironments::default()
+        }),
+        Err(_) => WorkspaceEnvironments::default(),
+    }
+}
+
+/// The request as sent, after substituting its variables.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CosmoResolvedRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: HashMap<String, String>,
+    pub body: Option<CosmoBody>,
+}
+
+/// How deeply variables may refer to other variables.
+const MAX_DEPTH: usize = 8;
+
+/// Substitutes `{{name}}` placeholders from a set of variables, and
+/// `{{$helper}}` placeholders with dynamic values. `\{{` stands for a
+/// literal `{{`, in the request and in variable values alike.
+///
+/// Values may refer to other variables. Every placeholder that can't be
+/// resolved, at any depth, is reported.
+struct Interpolator<'a> {
+    variables: &'a HashMap<String, String>,
+    dynamic: DynamicValues,
+    /// Placeholders that couldn't be resolved, with where they were found
+    unresolved: Vec<String>,
+}
+
+impl Interpolator<'_> {
+    fn interpolate(&mut self, text: &mut String, location: &str) {
+        if text.contains("{{") {
+            *text = self.expand(text, location, 0);
+        }
+    }
+
+    fn expand(&mut self, text: &str, location: &str, depth: usize) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                output.push_str(&rest[..start - 1]);
+                output.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            let Some(len) = rest[start + 2..].find("}}") else { break };
+            output.push_str(&rest[..start]);
+            let placeholder = rest[start + 2..start + 2 + len].trim();
+            let value = if placeholder.starts_with('$') {
+                match template::parse(placeholder) {
+                    Ok(call) => self.call(&call, placeholder, location, depth),
+                    Err(reason) => self.unresolved(placeholder, location, Some(&reason)),
+                }
+            } else {
+                self.variable(placeholder, location, depth)
+            };
+            if let Some(value) = value {
+                output.push_str(&value);
+            }
+            rest = &rest[start + 2 + len + 2..];
+        }
+        output.push_str(rest);
+        output
+    }
+
+    fn variable(&mut self, name: &str, location: &str, depth: usize) -> Option<String> {
+        match self.variables.get(name) {
+            Some(value) if depth < MAX_DEPTH => Some(self.expand(value, location, depth + 1)),
+            Some(_) => self.unresolved(name, location, Some("nested too deeply")),
+            None => self.unresolved(name, location, None),
+        }
+    }
+
+    fn call(&mut self, call: &HelperCall, placeholder: &str, location: &str, depth: usize) -> Option<String> {
+        let mut args = Vec::with_capacity(call.args.len());
+        for arg in &call.args {
+            args.push(match arg {
+                HelperArg::Text(text) => text.clone(),
+                HelperArg::Variable(name) => self.variable(name, location, depth)?,
+                HelperArg::Call(inner) => self.call(inner, placeholder, location, depth)?,
+            });
+        }
+        match self.dynamic.call(&call.name, &args) {
+            Ok(value) => Some(value),
+            Err(reason) => self.unresolved(placeholder, location, Some(&reason)),
+        }
+    }
+
+    fn unresolved(&mut self, placeholder: &str, location: &str, reason: Option<&str>) -> Option<String> {
+        let mut entry = format!("{{{{{}}}}} in {}", placeholder, location);
+        if let Some(reason) = reason {
+            entry.push_str(&format!(" ({})", reason));
+        }
+        if !self.unresolved.contains(&entry) {
+            self.unresolved.push(entry);
+        }
+        None
+    }
+}
+
+/// Substitutes the request's variables and dynamic values into its URL,
+/// headers and body.
+///
+/// Fails with a `ValidationError` naming every placeholder that has no value,
+/// so nothing is sent with a literal `{{name}}` in it; `\{{` sends a literal
+/// `{{`. Placeholders are
+/// evaluated in a fixed order, URL then headers by name then body, so a
+/// `template_seed` reproduces the same values.
+pub fn resolve_request(request: &mut CosmoRequest) -> Result<CosmoResolvedRequest, CosmoError> {
+    let mut interpolator = Interpolator {
+        variables: &request.variables,
+        dynamic: DynamicValues::new(request.template_seed),
+        unresolved: Vec::new(),
+    };
+
+    interpolator.interpolate(&mut request.url, "URL");
+    if let Some(headers) = request.headers.take() {
+        let mut headers: Vec<_> = headers.into_iter().collect();
+        headers.sort();
+        let mut resolved = HashMap::with_capacity(headers.len());
+        for (mut name, mut value) in headers {
+            interpolator.interpolate(&mut name, "header name");
+            interpolator.interpolate(&mut value, &format!("header {}", name));
+            resolved.insert(name, value);
+        }
+        request.headers = Some(resolved);
+    }
+    if let Some(body) = &mut request.body {
+        for text in body.texts_mut() {
+            interpolator.interpolate(text, "body");
+        }
+    }
+
+    if !interpolator.unresolved.is_empty() {
+        return Err(CosmoError::new(
+            CosmoErrorType::ValidationError,
+            format!("Unresolved variables: {}", interpolator.unresolved.join(", ")),
+        ));
+    }
+
+    Ok(CosmoResolvedRequest {
+        method: request.method.clone(),
+        url: request.url.clone(),
+        headers: request.headers.clone().unwrap_or_default(),
+        body: request.body.clone(),
+    })
+}
@@ -0,0 +1,76 @@
+use std::error::Error;
+use std::io;
+
+use rustls::CertificateError;
+
+use crate::timings::DnsLookupError;
+use crate::CosmoErrorType;
+
+/// Classifies a failure by the most specific cause in its source chain, e.g.
+/// the `rustls::Error` behind an I/O error. Returns `None` when no cause is
+/// recognized.
+pub fn classify(error: &(dyn Error + 'static)) -> Option<CosmoErrorType> {
+    chain(error).into_iter().filter_map(classify_cause).last()
+}
+
+/// Messages of the causes behind `error`, outermost first.
+pub fn causes(error: &(dyn Error + 'static)) -> Vec<String> {
+    let mut causes: Vec<String> = Vec::new();
+    let top = error.to_string();
+    for cause in chain(error).into_iter().skip(1) {
+        let message = cause.to_string();
+        // An io::Error displays the error it wraps, so skip the repeat
+        if message != top && causes.last() != Some(&message) {
+            causes.push(message);
+        }
+    }
+    causes
+}
+
+/// `error` followed by its sources. Unlike `source()`, this also steps into
+/// the error an `io::Error` wraps rather than skipping straight to its source.
+fn chain<'a>(error: &'a (dyn Error + 'static)) -> Vec<&'a (dyn Error + 'static)> {
+    let mut chain = Vec::new();
+    let mut next = Some(error);
+    while let Some(cause) = next {
+        chain.push(cause);
+        next = match cause.downcast_ref::<io::Error>().and_then(io::Error::get_ref) {
+            Some(inner) => Some(inner as &(dyn Error + 'static)),
+            None => cause.source(),
+        };
+    }
+    chain
+}
+
+fn classify_cause(cause: &(dyn Error + 'static)) -> Option<CosmoErrorType> {
+    if cause.is::<DnsLookupError>() {
+        return Some(CosmoErrorType::DnsError);
+    }
+    if let Some(e) = cause.downcast_ref::<rustls::Error>() {
+        return Some(classify_tls(e));
+    }
+    if let Some(e) = cause.downcast_ref::<hyper::Error>() {
+        return e.is_incomplete_message().then_some(CosmoErrorType::ConnectionReset);
+    }
+    match cause.downcast_ref::<io::Error>()?.kind() {
+        io::ErrorKind::ConnectionRefused => Some(CosmoErrorType::ConnectionRefused),
+        io::ErrorKind::ConnectionReset
+        | io::ErrorKind::ConnectionAborted
+        | io::ErrorKind::BrokenPipe
+        | io::ErrorKind::UnexpectedEof => Some(CosmoErrorType::ConnectionReset),
+        _ => None,
+    }
+}
+
+fn classify_tls(error: &rustls::Error) -> CosmoErrorType {
+    match error {
+        rustls::Error::InvalidCertificate(CertificateError::Expired | CertificateError::ExpiredContext { .. }) => {
+            CosmoErrorType::CertificateExpired
+        }
+        rustls::Error::InvalidCertificate(CertificateError::NotValidForName | CertificateError::NotValidForNameContext { .. }) => {
+            CosmoErrorType::HostnameMismatch
+        }
+        rustls::Error::InvalidCertificate(_) => CosmoErrorType::CertificateInvalid,
+        _ => CosmoErrorType::SslError,
+    }
+}
@@ -0,0 +1,260 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::request_body::CosmoBody;
+use crate::{CosmoError, CosmoErrorType, CosmoRequest};
+
+/// A GraphQL response split into its standard members.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CosmoGraphQLResult {
+    pub data: Option<Value>,
+    /// Errors reported by the server; empty when the operation succeeded
+    pub errors: Vec<CosmoGraphQLError>,
+    pub extensions: Option<Value>,
+}
+
+/// An entry of a GraphQL response's `errors` array.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmoGraphQLError {
+    pub message: String,
+    #[serde(default)]
+    pub locations: Vec<GraphQLLocation>,
+    /// Response path of the field that failed, as names and list indices
+    #[serde(default)]
+    pub path: Vec<Value>,
+    #[serde(default)]
+    pub extensions: Option<Value>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GraphQLLocation {
+    pub line: u32,
+    pub column: u32,
+}
+
+/// Encodes a GraphQL operation as the JSON body of a POST request.
+pub fn encode_operation(query: &str, variables: Option<&Value>, operation_name: Option<&str>) -> Vec<u8> {
+    let mut body = serde_json::Map::new();
+    body.insert("query".to_string(), Value::String(query.to_string()));
+    if let Some(variables) = variables {
+        body.insert("variables".to_string(), variables.clone());
+    }
+    if let Some(operation_name) = operation_name {
+        body.insert("operationName".to_string(), Value::String(operation_name.to_string()));
+    }
+    Value::Object(body).to_string().into_bytes()
+}
+
+/// Parses a GraphQL response body. Returns `None` when the body isn't a JSON
+/// object with `data` or `errors`, e.g. for a proxy's HTML error page.
+pub fn parse_result(body: &str) -> Option<CosmoGraphQLResult> {
+    let mut response = match serde_json::from_str::<Value>(body).ok()? {
+        Value::Object(response) => response,
+        _ => return None,
+    };
+    if !response.contains_key("data") && !response.contains_key("errors") {
+        return None;
+    }
+
+    let errors = match response.remove("errors") {
+        Some(Value::Array(errors)) => errors
+            .into_iter()
+            .map(|error| {
+                serde_json::from_value(error.clone()).unwrap_or_else(|_| CosmoGraphQLError {
+                    message: error.to_string(),
+                    locations: Vec::new(),
+                    path: Vec::new(),
+                    extensions: None,
+                })
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+
+    Some(CosmoGraphQLResult {
+        data: response.remove("data").filter(|data| !data.is_null()),
+        errors,
+        extensions: response.remove("extensions"),
+    })
+}
+
+/// Schema summary built from an introspection query, for autocompletion.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphQLSchemaSummary {
+    pub query_type: Option<String>,
+    pub mutation_type: Option<String>,
+    pub subscription_type: Option<String>,
+    /// Named types, excluding the introspection types (`__Schema` etc.)
+    pub types: Vec<GraphQLTypeSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphQLTypeSummary {
+    pub name: String,
+    /// `OBJECT`, `INTERFACE`, `UNION`, `ENUM`, `INPUT_OBJECT` or `SCALAR`
+    pub kind: String,
+    pub description: Option<String>,
+    /// Fields of objects and interfaces
+    pub fields: Vec<GraphQLFieldSummary>,
+    /// Fields of input objects
+    pub input_fields: Vec<GraphQLInputValue>,
+    pub enum_values: Vec<String>,
+    /// Members of unions and implementations of interfaces
+    pub possible_types: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphQLFieldSummary {
+    pub name: String,
+    pub description: Option<String>,
+    /// Type in SDL notation, e.g. `[User!]!`
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub args: Vec<GraphQLInputValue>,
+    pub is_deprecated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GraphQLInputValue {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "type")]
+    pub type_name: String,
+    pub default_value: Option<String>,
+}
+
+/// Standard introspection query, covering type references up to seven
+/// wrappers deep (e.g. `[[Int!]!]!`).
+pub const INTROSPECTION_QUERY: &str = r#"query IntrospectionQuery {
+  __schema {
+    queryType { name }
+    mutationType { name }
+    subscriptionType { name }
+    types {
+      kind
+      name
+      description
+      fields(includeDeprecated: true) {
+        name
+        description
+        args { ...InputValue }
+        type { ...TypeRef }
+        isDeprecated
+      }
+      inputFields { ...InputValue }
+      enumValues(includeDeprecated: true) { name }
+      possibleTypes { name }
+    }
+  }
+}
+
+fragment InputValue on __InputValue {
+  name
+  description
+  type { ...TypeRef }
+  defaultValue
+}
+
+fragment TypeRef on __Type {
+  kind
+  name
+  ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name ofType { kind name } } } } } } }
+}"#;
+
+/// Builds the schema summary from the `data` of an introspection response.
+pub fn summarize_schema(data: &Value) -> Result<GraphQLSchemaSummary, String> {
+    let schema = data
+        .get("__schema")
+        .ok_or_else(|| "Introspection response has no __schema".to_string())?;
+    let root_name = |key: &str| schema.get(key).and_then(|t| str_field(t, "name"));
+
+    let types = schema
+        .get("types")
+        .and_then(Value::as_array)
+        .ok_or_else(|| "Introspection response has no types".to_string())?
+        .iter()
+        .filter(|t| str_field(t, "name").is_some_and(|name| !name.starts_with("__")))
+        .map(|t| GraphQLTypeSummary {
+            name: str_field(t, "name").unwrap_or_default(),
+            kind: str_field(t, "kind").unwrap_or_default(),
+            description: str_field(t, "description"),
+            fields: array_field(t, "fields")
+                .map(|f| GraphQLFieldSummary {
+                    name: str_field(f, "name").unwrap_or_default(),
+                    description: str_field(f, "description"),
+                    type_name: render_type(f.get("type")),
+                    args: array_field(f, "args").map(input_value).collect(),
+                    is_deprecated: f.get("isDeprecated").and_then(Value::as_bool).unwrap_or(false),
+                })
+                .collect(),
+            input_fields: array_field(t, "inputFields").map(input_value).collect(),
+            enum_values: array_field(t, "enumValues").filter_map(|v| str_field(v, "name")).collect(),
+            possible_types: array_field(t, "possibleTypes").filter_map(|v| str_field(v, "name")).collect(),
+        })
+        .collect();
+
+    Ok(GraphQLSchemaSummary {
+        query_type: root_name("queryType"),
+        mutation_type: root_name("mutationType"),
+        subscription_type: root_name("subscriptionType"),
+        types,
+    })
+}
+
+fn str_field(value: &Value, key: &str) -> Option<String> {
+    value.get(key).and_then(Value::as_str).map(str::to_string)
+}
+
+fn array_field<'a>(value: &'a Value, key: &str) -> impl Iterator<Item = &'a Value> {
+    value.get(key).and_then(Value::as_array).into_iter().flatten()
+}
+
+fn input_value(value: &Value) -> GraphQLInputValue {
+    GraphQLInputValue {
+        name: str_field(value, "name").unwrap_or_default(),
+        description: str_field(value, "description"),
+        type_name: render_type(value.get("type")),
+        default_value: str_field(value, "defaultValue"),
+    }
+}
+
+/// Renders an introspection type reference in SDL notation.
+fn render_type(type_ref: Option<&Value>) -> String {
+    let type_ref = match type_ref {
+        Some(type_ref) => type_ref,
+        None => return String::new(),
+    };
+    match type_ref.get("kind").and_then(Value::as_str) {
+        Some("NON_NULL") => format!("{}!", render_type(type_ref.get("ofType"))),
+        Some("LIST") => format!("[{}]", render_type(type_ref.get("ofType"))),
+        _ => str_field(type_ref, "name").unwrap_or_default(),
+    }
+}
+
+/// Runs the introspection query against `request.url`, with the request's
+/// headers and settings, and summarizes the schema.
+pub async fn introspect(app_handle: Option<tauri::AppHandle>, request: CosmoRequest) -> Result<GraphQLSchemaSummary, CosmoError> {
+    let request = CosmoRequest {
+        method: "POST".to_string(),
+        body: Some(CosmoBody::Graphql {
+            query: INTROSPECTION_QUERY.to_string(),
+            variables: None,
+            operation_name: Some("IntrospectionQuery".to_string()),
+        }),
+        ..request
+    };
+    let response = crate::do_execute_cosmo_request(app_handle, request).await?;
+
+    let graphql_error = |message: String| CosmoError::new(CosmoErrorType::ValidationError, message);
+    let result = response
+        .graphql
+        .ok_or_else(|| graphql_error(format!("Introspection failed with status {}: not a GraphQL response", response.status)))?;
+    let data = match result.data {
+        Some(data) => data,
+        None => {
+            let messages: Vec<&str> = result.errors.iter().map(|e| e.message.as_str()).collect();
+            return Err(graphql_error(format!("Introspection failed: {}", messages.join("; "))));
+        }
+    };
+    summarize_schema(&data).map_err(graphql_error)
+}
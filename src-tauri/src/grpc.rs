@@ -0,0 +1,653 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use hyper_util::rt::TokioIo;
+use prost_reflect::prost::Message as _;
+use prost_reflect::prost_types::{FileDescriptorProto, FileDescriptorSet};
+use prost_reflect::{DescriptorPool, DynamicMessage, MessageDescriptor, MethodDescriptor, ReflectMessage, SerializeOptions};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tonic::codec::{Codec, DecodeBuf, Decoder, EncodeBuf, Encoder};
+use tonic::codegen::http::uri::PathAndQuery;
+use tonic::codegen::{BoxFuture, Context, Poll, Service, StdError};
+use tonic::metadata::{AsciiMetadataKey, BinaryMetadataKey, MetadataMap, MetadataValue};
+use tonic::transport::{Channel, Endpoint, Uri};
+use tonic::Status;
+use tonic_reflection::pb::v1::server_reflection_client::ServerReflectionClient;
+use tonic_reflection::pb::v1::server_reflection_request::MessageRequest;
+use tonic_reflection::pb::v1::server_reflection_response::MessageResponse;
+use tonic_reflection::pb::v1::ServerReflectionRequest;
+use uuid::Uuid;
+
+use crate::inflight::InFlightRequests;
+use crate::tls::{self, CosmoTlsSettings};
+use crate::{error_chain, CosmoError, CosmoErrorType, CosmoHeader, CosmoTimeouts, WorkspaceContext};
+
+/// Where the service definitions for a call come from.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CosmoProtoSource {
+    /// `.proto` files parsed when the call is made; imports are resolved
+    /// against `include_paths`, then the directories of the files themselves
+    Files {
+        paths: Vec<String>,
+        #[serde(default)]
+        include_paths: Vec<String>,
+    },
+    /// The server's reflection service (`grpc.reflection.v1`)
+    #[default]
+    Reflection,
+}
+
+/// A gRPC call sent from the frontend.
+#[derive(Debug, Default, Deserialize)]
+pub struct CosmoGrpcRequest {
+    /// Server address, e.g. `http://localhost:50051`; `https://` connects over
+    /// TLS and a missing scheme means `http://`
+    pub url: String,
+    /// Method to call as `package.Service/Method`
+    pub method: String,
+    #[serde(default)]
+    pub source: CosmoProtoSource,
+    /// Request metadata; values of keys ending in `-bin` are base64 encoded
+    #[serde(default)]
+    pub metadata: Option<HashMap<String, String>>,
+    /// Request messages as JSON, in the protobuf JSON mapping. Unary and
+    /// server-streaming methods take exactly one.
+    #[serde(default)]
+    pub messages: Vec<Value>,
+    /// Optional timeout limits; `total_ms` is also sent as the call's deadline
+    #[serde(default)]
+    pub timeouts: Option<CosmoTimeouts>,
+    /// Caller-supplied id used to cancel the call; generated when absent
+    #[serde(default)]
+    pub request_id: Option<String>,
+    /// TLS settings of the workspace, set by the command
+    #[serde(skip)]
+    pub tls: CosmoTlsSettings,
+}
+
+/// Whether each side of a method sends one message or a stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GrpcCallKind {
+    Unary,
+    ServerStreaming,
+    ClientStreaming,
+    BidiStreaming,
+}
+
+impl GrpcCallKind {
+    fn of(method: &MethodDescriptor) -> GrpcCallKind {
+        match (method.is_client_streaming(), method.is_server_streaming()) {
+            (false, false) => GrpcCallKind::Unary,
+            (false, true) => GrpcCallKind::ServerStreaming,
+            (true, false) => GrpcCallKind::ClientStreaming,
+            (true, true) => GrpcCallKind::BidiStreaming,
+        }
+    }
+}
+
+/// Outcome of a gRPC call. A call the server fails with a non-OK status is
+/// still a response; only transport and setup failures are errors.
+#[derive(Debug, Serialize)]
+pub struct CosmoGrpcResponse {
+    /// gRPC status code; 0 (`OK`) on success
+    pub status_code: i32,
+    /// Canonical name of the status code, e.g. `NOT_FOUND`
+    pub status: String,
+    pub status_message: String,
+    pub kind: GrpcCallKind,
+    /// Response messages as JSON, in the order received
+    pub messages: Vec<Value>,
+    /// Response headers (initial metadata)
+    pub headers: Vec<CosmoHeader>,
+    /// Trailing metadata, without `grpc-status` and `grpc-message`. For a
+    /// trailers-only response this holds all of the response's metadata.
+    pub trailers: Vec<CosmoHeader>,
+    /// Total wall time in milliseconds, including loading descriptors
+    pub duration_ms: u128,
+    pub timings: CosmoGrpcTimings,
+    /// Id the call was tracked under, usable with `cancel_cosmo_request`
+    pub request_id: Option<String>,
+}
+
+/// Timing breakdown of a gRPC call, in milliseconds.
+#[derive(Debug, Clone, Serialize)]
+pub struct CosmoGrpcTimings {
+    /// Establishing the connection, including the TLS handshake
+    pub connect_ms: u128,
+    /// Loading descriptors from files or over reflection
+    pub descriptors_ms: u128,
+    /// From sending the call until the response headers arrived
+    pub ttfb_ms: u128,
+    /// From the response headers until the trailers arrived
+    pub download_ms: u128,
+    pub total_ms: u128,
+}
+
+/// A service found in the loaded descriptors.
+#[derive(Debug, Serialize)]
+pub struct GrpcServiceSummary {
+    /// Fully-qualified name, e.g. `helloworld.Greeter`
+    pub name: String,
+    pub methods: Vec<GrpcMethodSummary>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GrpcMethodSummary {
+    pub name: String,
+    /// Value for `CosmoGrpcRequest::method`
+    pub full_name: String,
+    pub kind: GrpcCallKind,
+    pub input_type: String,
+    pub output_type: String,
+    /// The input message with every field at its default, as a starting point
+    pub input_template: Value,
+}
+
+/// Encodes and decodes messages whose types are only known at runtime.
+#[derive(Debug, Clone)]
+pub struct DynamicCodec {
+    decode: MessageDescriptor,
+}
+
+impl DynamicCodec {
+    /// A codec decoding messages of type `decode`; any message is encoded.
+    pub fn new(decode: MessageDescriptor) -> DynamicCodec {
+        DynamicCodec { decode }
+    }
+}
+
+impl Codec for DynamicCodec {
+    type Encode = DynamicMessage;
+    type Decode = DynamicMessage;
+    type Encoder = DynamicEncoder;
+    type Decoder = DynamicDecoder;
+
+    fn encoder(&mut self) -> DynamicEncoder {
+        DynamicEncoder
+    }
+
+    fn decoder(&mut self) -> DynamicDecoder {
+        DynamicDecoder(self.decode.clone())
+    }
+}
+
+pub struct DynamicEncoder;
+
+impl Encoder for DynamicEncoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn encode(&mut self, item: DynamicMessage, dst: &mut EncodeBuf<'_>) -> Result<(), Status> {
+        item.encode(dst).map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+pub struct DynamicDecoder(MessageDescriptor);
+
+impl Decoder for DynamicDecoder {
+    type Item = DynamicMessage;
+    type Error = Status;
+
+    fn decode(&mut self, src: &mut DecodeBuf<'_>) -> Result<Option<DynamicMessage>, Status> {
+        DynamicMessage::decode(self.0.clone(), src)
+            .map(Some)
+            .map_err(|e| Status::internal(e.to_string()))
+    }
+}
+
+/// Performs a gRPC call, bounded by `total_ms` when set.
+pub async fn execute(request: CosmoGrpcRequest) -> Result<CosmoGrpcResponse, CosmoError> {
+    match request.timeouts.and_then(|t| t.total_ms) {
+        Some(ms) => tokio::time::timeout(Duration::from_millis(ms), send_grpc_request(request))
+            .await
+            .unwrap_or_else(|_| Err(CosmoError::timeout("total", Some(ms)))),
+        None => send_grpc_request(request).await,
+    }
+}
+
+async fn send_grpc_request(request: CosmoGrpcRequest) -> Result<CosmoGrpcResponse, CosmoError> {
+    let start = Instant::now();
+    let timeouts = request.timeouts.unwrap_or_default();
+    let (service_name, method_name) = split_method(&request.method)?;
+    let metadata = build_metadata(request.metadata.as_ref())?;
+
+    let channel = connect(&request.url, &request.tls, timeouts).await?;
+    let connected_at = Instant::now();
+
+    let pool = load_descriptors(&request.source, &channel, &metadata, Some(&service_name)).await?;
+    let method = pool
+        .get_service_by_name(&service_name)
+        .ok_or_else(|| invalid_call(format!("Service '{}' not found", service_name)))?
+        .methods()
+        .find(|m| m.name() == method_name)
+        .ok_or_else(|| invalid_call(format!("Method '{}' not found in service '{}'", method_name, service_name)))?;
+    let kind = GrpcCallKind::of(&method);
+    if !method.is_client_streaming() && request.messages.len() != 1 {
+        return Err(invalid_call(format!(
+            "{} takes exactly one request message, got {}",
+            method.full_name(),
+            request.messages.len()
+        )));
+    }
+    let messages = request
+        .messages
+        .into_iter()
+        .map(|json| {
+            DynamicMessage::deserialize(method.input(), json)
+                .map_err(|e| invalid_call(format!("Invalid {} message: {}", method.input().full_name(), e)))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let descriptors_at = Instant::now();
+
+    let path = PathAndQuery::try_from(format!("/{}/{}", method.parent_service().full_name(), method.name()))
+        .map_err(|e| invalid_call(format!("Invalid method path: {}", e)))?;
+    let mut call = tonic::Request::new(futures_util::stream::iter(messages));
+    *call.metadata_mut() = metadata;
+    if let Some(ms) = timeouts.total_ms {
+        call.set_timeout(Duration::from_millis(ms));
+    }
+
+    let mut grpc = tonic::client::Grpc::new(channel);
+    grpc.ready().await.map_err(|e| {
+        CosmoError::caused_by(CosmoErrorType::NetworkError, format!("Connection is not ready: {}", error_chain(&e)), &e)
+    })?;
+
+    let mut headers = Vec::new();
+    let mut responses = Vec::new();
+    let mut trailers = MetadataMap::new();
+    let mut status = Status::ok("");
+    let mut headers_at = None;
+    match grpc.streaming(call, path, DynamicCodec::new(method.output())).await {
+        Ok(response) => {
+            headers_at = Some(Instant::now());
+            headers = metadata_headers(response.metadata());
+            let mut stream = response.into_inner();
+            loop {
+                match stream.message().await {
+                    Ok(Some(message)) => responses.push(message_json(&message)?),
+                    Ok(None) => {
+                        if let Ok(Some(received)) = stream.trailers().await {
+                            trailers = received;
+                        }
+                        break;
+                    }
+                    Err(failed) => {
+                        status = failed;
+                        break;
+                    }
+                }
+            }
+        }
+        Err(failed) => status = failed,
+    }
+    if status.code() != tonic::Code::Ok {
+        trailers = status.metadata().clone();
+    }
+    let end = Instant::now();
+    let headers_at = headers_at.unwrap_or(end);
+
+    Ok(CosmoGrpcResponse {
+        status_code: status.code() as i32,
+        status: code_name(status.code()),
+        status_message: status.message().to_string(),
+        kind,
+        messages: responses,
+        headers,
+        trailers: metadata_headers(&trailers),
+        duration_ms: end.duration_since(start).as_millis(),
+        timings: CosmoGrpcTimings {
+            connect_ms: connected_at.duration_since(start).as_millis(),
+            descriptors_ms: descriptors_at.duration_since(connected_at).as_millis(),
+            ttfb_ms: headers_at.duration_since(descriptors_at).as_millis(),
+            download_ms: end.duration_since(headers_at).as_millis(),
+            total_ms: end.duration_since(start).as_millis(),
+        },
+        request_id: request.request_id,
+    })
+}
+
+/// Lists the services and methods available from `source`. With reflection,
+/// every service the server advertises is loaded.
+pub async fn describe(request: CosmoGrpcRequest) -> Result<Vec<GrpcServiceSummary>, CosmoError> {
+    let pool = match &request.source {
+        // Files can be browsed without a server to connect to
+        CosmoProtoSource::Files { paths, include_paths } => load_proto_files(paths, include_paths).map_err(files_error)?,
+        CosmoProtoSource::Reflection => {
+            let metadata = build_metadata(request.metadata.as_ref())?;
+            let channel = connect(&request.url, &request.tls, request.timeouts.unwrap_or_default()).await?;
+            load_descriptors(&request.source, &channel, &metadata, None).await?
+        }
+    };
+
+    pool.services()
+        .filter(|service| !service.full_name().starts_with("grpc.reflection."))
+        .map(|service| {
+            let methods = service
+                .methods()
+                .map(|method| {
+                    Ok(GrpcMethodSummary {
+                        name: method.name().to_string(),
+                        full_name: format!("{}/{}", service.full_name(), method.name()),
+                        kind: GrpcCallKind::of(&method),
+                        input_type: method.input().full_name().to_string(),
+                        output_type: method.output().full_name().to_string(),
+                        input_template: message_json(&DynamicMessage::new(method.input()))?,
+                    })
+                })
+                .collect::<Result<_, CosmoError>>()?;
+            Ok(GrpcServiceSummary {
+                name: service.full_name().to_string(),
+                methods,
+            })
+        })
+        .collect()
+}
+
+/// Splits `package.Service/Method` (or `package.Service.Method`) into the
+/// service's full name and the method name.
+fn split_method(method: &str) -> Result<(String, String), CosmoError> {
+    let method = method.trim().trim_start_matches('/');
+    method
+        .rsplit_once('/')
+        .or_else(|| method.rsplit_once('.'))
+        .filter(|(service, name)| !service.is_empty() && !name.is_empty())
+        .map(|(service, name)| (service.to_string(), name.to_string()))
+        .ok_or_else(|| invalid_call(format!("Invalid method '{}': expected package.Service/Method", method)))
+}
+
+fn setup_error(message: String) -> CosmoError {
+    CosmoError::new(CosmoErrorType::UnknownError, message)
+}
+
+/// A call that can't be made as given, e.g. an unknown method or a message
+/// that doesn't match its type.
+fn invalid_call(message: String) -> CosmoError {
+    CosmoError::new(CosmoErrorType::ValidationError, message)
+}
+
+fn files_error(message: String) -> CosmoError {
+    invalid_call(format!("Failed to load .proto files: {}", message))
+}
+
+fn build_metadata(entries: Option<&HashMap<String, String>>) -> Result<MetadataMap, CosmoError> {
+    let mut metadata = MetadataMap::new();
+    for (name, value) in entries.into_iter().flatten() {
+        let invalid = || invalid_call(format!("Invalid metadata '{}'", name));
+        let key = name.trim().to_ascii_lowercase();
+        if key.ends_with("-bin") {
+            let key = BinaryMetadataKey::from_bytes(key.as_bytes()).map_err(|_| invalid())?;
+            let bytes = STANDARD.decode(value.trim()).map_err(|_| invalid())?;
+            metadata.append_bin(key, MetadataValue::from_bytes(&bytes));
+        } else {
+            let key = AsciiMetadataKey::from_bytes(key.as_bytes()).map_err(|_| invalid())?;
+            metadata.append(key, value.parse().map_err(|_| invalid())?);
+        }
+    }
+    Ok(metadata)
+}
+
+/// Reports metadata as header lines, leaving out the status trailers.
+fn metadata_headers(metadata: &MetadataMap) -> Vec<CosmoHeader> {
+    metadata
+        .clone()
+        .into_headers()
+        .iter()
+        .filter(|(name, _)| !matches!(name.as_str(), "grpc-status" | "grpc-message" | "grpc-status-details-bin"))
+        .map(|(name, value)| CosmoHeader::from_pair(name, value))
+        .collect()
+}
+
+/// Renders a message in the protobuf JSON mapping, including default values.
+fn message_json(message: &DynamicMessage) -> Result<Value, CosmoError> {
+    message
+        .serialize_with_options(serde_json::value::Serializer, &SerializeOptions::new().skip_default_fields(false))
+        .map_err(|e| setup_error(format!("Failed to convert {} to JSON: {}", message.descriptor().full_name(), e)))
+}
+
+/// Canonical upper-case name of a status code, e.g. `DEADLINE_EXCEEDED`.
+fn code_name(code: tonic::Code) -> String {
+    let mut name = String::new();
+    for (i, c) in format!("{:?}", code).chars().enumerate() {
+        if i > 0 && c.is_ascii_uppercase() {
+            name.push('_');
+        }
+        name.push(c.to_ascii_uppercase());
+    }
+    name
+}
+
+async fn connect(url: &str, tls_settings: &CosmoTlsSettings, timeouts: CosmoTimeouts) -> Result<Channel, CosmoError> {
+    let url = url.trim();
+    let url = if url.contains("://") { url.to_string() } else { format!("http://{}", url) };
+    let invalid_url = |detail: String| CosmoError::new(
+        CosmoErrorType::InvalidUrl,
+        format!("Invalid gRPC server address: {}", detail),
+    );
+    let uri: Uri = url.parse().map_err(|e: tonic::codegen::http::uri::InvalidUri| invalid_url(e.to_string()))?;
+    let host = uri.host().ok_or_else(|| invalid_url("missing host".to_string()))?;
+
+    let tls_config = match uri.scheme_str() {
+        Some("https") => {
+            let mut config = tls::client_config(&tls_settings.for_host(host.trim_matches(['[', ']']))).map_err(|e| CosmoError::new(
+                CosmoErrorType::SslError,
+                format!("Failed to initialize TLS: {}", e),
+            ))?;
+            // gRPC runs over HTTP/2 only
+            config.alpn_protocols = vec![b"h2".to_vec()];
+            Some(Arc::new(config))
+        }
+        Some("http") => None,
+        _ => return Err(invalid_url(format!("unsupported scheme in '{}'", url))),
+    };
+
+    let mut endpoint = Endpoint::from(uri);
+    if let Some(ms) = timeouts.connect_ms {
+        endpoint = endpoint.connect_timeout(Duration::from_millis(ms));
+    }
+    endpoint
+        .connect_with_connector(Connector { tls: tls_config })
+        .await
+        .map_err(|e| connect_error(&e, &url, timeouts))
+}
+
+fn connect_error(e: &tonic::transport::Error, url: &str, timeouts: CosmoTimeouts) -> CosmoError {
+    let mut source: Option<&(dyn std::error::Error + 'static)> = Some(e);
+    while let Some(cause) = source {
+        if cause.downcast_ref::<std::io::Error>().is_some_and(|io| io.kind() == std::io::ErrorKind::TimedOut) {
+            return CosmoError::timeout("connect", timeouts.connect_ms);
+        }
+        source = cause.source();
+    }
+    CosmoError::caused_by(CosmoErrorType::NetworkError, format!("Failed to connect to {}: {}", url, error_chain(e)), e)
+}
+
+trait Io: AsyncRead + AsyncWrite + Send + Unpin {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin> Io for T {}
+
+/// Opens connections for a channel, using the workspace's TLS settings for
+/// `https` addresses.
+#[derive(Clone)]
+struct Connector {
+    tls: Option<Arc<rustls::ClientConfig>>,
+}
+
+impl Service<Uri> for Connector {
+    type Response = TokioIo<Box<dyn Io>>;
+    type Error = StdError;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let tls = self.tls.clone();
+        Box::pin(async move {
+            let host = uri.host().ok_or("missing host")?.trim_matches(['[', ']']).to_string();
+            let port = uri.port_u16().unwrap_or(if tls.is_some() { 443 } else { 80 });
+            let tcp = tokio::net::TcpStream::connect((host.as_str(), port)).await?;
+            tcp.set_nodelay(true)?;
+            let io: Box<dyn Io> = match tls {
+                Some(config) => {
+                    let server_name = rustls::pki_types::ServerName::try_from(host)?;
+                    Box::new(tokio_rustls::TlsConnector::from(config).connect(server_name, tcp).await?)
+                }
+                None => Box::new(tcp),
+            };
+            Ok(TokioIo::new(io))
+        })
+    }
+}
+
+/// Builds a descriptor pool from `source`. Over reflection, only the files
+/// defining `symbol` (and their imports) are fetched when it is given, and
+/// every advertised service otherwise.
+async fn load_descriptors(
+    source: &CosmoProtoSource,
+    channel: &Channel,
+    metadata: &MetadataMap,
+    symbol: Option<&str>,
+) -> Result<DescriptorPool, CosmoError> {
+    match source {
+        CosmoProtoSource::Files { paths, include_paths } => load_proto_files(paths, include_paths).map_err(files_error),
+        CosmoProtoSource::Reflection => {
+            let mut client = ServerReflectionClient::new(channel.clone());
+            let symbols = match symbol {
+                Some(symbol) => vec![symbol.to_string()],
+                None => match reflect(&mut client, metadata, MessageRequest::ListServices(String::new())).await? {
+                    MessageResponse::ListServicesResponse(list) => list.service.into_iter().map(|s| s.name).collect(),
+                    _ => return Err(setup_error("Unexpected reply to a reflection service listing".to_string())),
+                },
+            };
+            load_from_reflection(&mut client, metadata, symbols).await
+        }
+    }
+}
+
+/// Parses `.proto` files with the built-in parser; the well-known
+/// `google/protobuf/*.proto` imports are always available.
+pub fn load_proto_files(paths: &[String], include_paths: &[String]) -> Result<DescriptorPool, String> {
+    if paths.is_empty() {
+        return Err("no files given".to_string());
+    }
+    let mut includes: Vec<&Path> = include_paths.iter().map(Path::new).collect();
+    for path in paths {
+        includes.push(Path::new(path).parent().filter(|dir| !dir.as_os_str().is_empty()).unwrap_or(Path::new(".")));
+    }
+
+    let mut compiler = protox::Compiler::new(includes).map_err(|e| e.to_string())?;
+    compiler.include_imports(true).open_files(paths).map_err(|e| e.to_string())?;
+    Ok(compiler.descriptor_pool())
+}
+
+/// Fetches the files defining `symbols` and everything they import.
+async fn load_from_reflection(
+    client: &mut ServerReflectionClient<Channel>,
+    metadata: &MetadataMap,
+    symbols: Vec<String>,
+) -> Result<DescriptorPool, CosmoError> {
+    let mut files: HashMap<String, FileDescriptorProto> = HashMap::new();
+    let mut requested = HashSet::new();
+    let mut pending: Vec<MessageRequest> = symbols.into_iter().map(MessageRequest::FileContainingSymbol).collect();
+
+    while let Some(message_request) = pending.pop() {
+        let encoded_files = match reflect(client, metadata, message_request).await? {
+            MessageResponse::FileDescriptorResponse(response) => response.file_descriptor_proto,
+            _ => return Err(setup_error("Unexpected reply to a reflection file request".to_string())),
+        };
+        for encoded in encoded_files {
+            let file = FileDescriptorProto::decode(encoded.as_slice())
+                .map_err(|e| setup_error(format!("Invalid descriptor from reflection: {}", e)))?;
+            for dependency in &file.dependency {
+                if !files.contains_key(dependency) && requested.insert(dependency.clone()) {
+                    pending.push(MessageRequest::FileByFilename(dependency.clone()));
+                }
+            }
+            requested.insert(file.name().to_string());
+            files.insert(file.name().to_string(), file);
+        }
+    }
+
+    DescriptorPool::from_file_descriptor_set(FileDescriptorSet {
+        file: files.into_values().collect(),
+    })
+    .map_err(|e| setup_error(format!("Invalid descriptors from reflection: {}", e)))
+}
+
+/// Sends one reflection request and returns its reply.
+async fn reflect(
+    client: &mut ServerReflectionClient<Channel>,
+    metadata: &MetadataMap,
+    message_request: MessageRequest,
+) -> Result<MessageResponse, CosmoError> {
+    let reflection_error = |status: Status| {
+        let message = match status.code() {
+            tonic::Code::Unimplemented => "Server reflection is not enabled on this server".to_string(),
+            _ => format!("Server reflection failed: {}", status.message()),
+        };
+        setup_error(message)
+    };
+
+    let mut call = tonic::Request::new(futures_util::stream::iter([ServerReflectionRequest {
+        host: String::new(),
+        message_request: Some(message_request),
+    }]));
+    *call.metadata_mut() = metadata.clone();
+    let mut replies = client.server_reflection_info(call).await.map_err(reflection_error)?.into_inner();
+    match replies.message().await.map_err(reflection_error)?.and_then(|reply| reply.message_response) {
+        Some(MessageResponse::ErrorResponse(error)) => Err(setup_error(format!("Server reflection failed: {}", error.error_message))),
+        Some(response) => Ok(response),
+        None => Err(setup_error("Server reflection returned no reply".to_string())),
+    }
+}
+
+/// Fills in timeouts and TLS settings from the workspace preferences when a
+/// user and workspace are given.
+async fn apply_workspace_preferences(app_handle: &tauri::AppHandle, request: &mut CosmoGrpcRequest, user_id: Option<String>, workspace_id: Option<String>) {
+    if let Some(context) = WorkspaceContext::load(app_handle, user_id, workspace_id).await {
+        let prefs = context.preferences;
+        request.timeouts = Some(request.timeouts.unwrap_or_default().or(prefs.request_timeouts));
+        request.tls = prefs.tls;
+    }
+}
+
+/// Performs a unary, server-streaming or client-streaming gRPC call. The
+/// call is tracked under its request id so `cancel_cosmo_request` can abort it.
+#[tauri::command]
+pub async fn execute_grpc_request(
+    app_handle: tauri::AppHandle,
+    in_flight: tauri::State<'_, InFlightRequests>,
+    request: CosmoGrpcRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<CosmoGrpcResponse, CosmoError> {
+    let mut request = request;
+    apply_workspace_preferences(&app_handle, &mut request, user_id, workspace_id).await;
+    let request_id = request
+        .request_id
+        .get_or_insert_with(|| Uuid::new_v4().to_string())
+        .clone();
+    in_flight.run(&request_id, execute(request)).await
+}
+
+/// Lists the services and methods of a server, from `.proto` files or
+/// reflection. Only `url`, `source`, `metadata` and `timeouts` are used.
+#[tauri::command]
+pub async fn describe_grpc_services(
+    app_handle: tauri::AppHandle,
+    request: CosmoGrpcRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<Vec<GrpcServiceSummary>, CosmoError> {
+    let mut request = request;
+    apply_workspace_preferences(&app_handle, &mut request, user_id, workspace_id).await;
+    describe(request).await
+}
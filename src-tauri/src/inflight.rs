@@ -0,0 +1,139 @@
+use futures_util::future::{AbortHandle, AbortRegistration, Abortable};
+use futures_util::FutureExt;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+
+use crate::{CosmoError, CosmoErrorType};
+
+/// Abort handles owned by a single request id.
+#[derive(Default)]
+struct InFlightEntry {
+    /// The request future itself, until the response is returned
+    request: Option<AbortHandle>,
+    /// A streaming task spawned for the response and the channel it emits on,
+    /// until the stream ends
+    stream: Option<(String, AbortHandle)>,
+}
+
+impl InFlightEntry {
+    fn is_empty(&self) -> bool {
+        self.request.is_none() && self.stream.is_none()
+    }
+}
+
+/// Registry of in-flight request executions, managed as Tauri state so that
+/// `cancel_cosmo_request` can abort them by id.
+#[derive(Default)]
+pub struct InFlightRequests {
+    entries: Mutex<HashMap<String, InFlightEntry>>,
+}
+
+impl InFlightRequests {
+    /// Runs `fut` tracked under `request_id`.
+    ///
+    /// Returns a `Cancelled` error if the request is cancelled before it completes,
+    /// and rejects ids that are still in flight.
+    pub async fn run<T, F>(&self, request_id: &str, fut: F) -> Result<T, CosmoError>
+    where
+        F: Future<Output = Result<T, CosmoError>>,
+    {
+        let registration = self.register(request_id)?;
+        let result = Abortable::new(fut, registration).await;
+        self.release(request_id, |entry| entry.request = None);
+
+        result.unwrap_or_else(|_| Err(CosmoError::cancelled(request_id)))
+    }
+
+    /// Runs the future `start` builds, tracked under `request_id`, without
+    /// aborting it on cancellation. Instead `start` is given a future that
+    /// resolves once the id is cancelled, so the work can wind down and still
+    /// return what it has.
+    pub async fn run_until_cancelled<T, S, F>(&self, request_id: &str, start: S) -> Result<T, CosmoError>
+    where
+        S: FnOnce(futures_util::future::BoxFuture<'static, ()>) -> F,
+        F: Future<Output = T>,
+    {
+        let registration = self.register(request_id)?;
+        let cancelled = Abortable::new(std::future::pending::<()>(), registration).map(|_| ());
+        let result = start(cancelled.boxed()).await;
+        self.release(request_id, |entry| entry.request = None);
+        Ok(result)
+    }
+
+    fn register(&self, request_id: &str) -> Result<AbortRegistration, CosmoError> {
+        let (handle, registration) = AbortHandle::new_pair();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.contains_key(request_id) {
+            return Err(CosmoError::new(
+                CosmoErrorType::ValidationError,
+                format!("Request id '{}' is already in flight", request_id),
+            ));
+        }
+        entries.insert(request_id.to_string(), InFlightEntry {
+            request: Some(handle),
+            stream: None,
+        });
+        Ok(registration)
+    }
+
+    /// Tracks a streaming task spawned while serving `request_id`.
+    pub fn track_stream(&self, request_id: &str, channel_id: &str, handle: AbortHandle) {
+        self.entries
+            .lock()
+            .unwrap()
+            .entry(request_id.to_string())
+            .or_default()
+            .stream = Some((channel_id.to_string(), handle));
+    }
+
+    /// Stops tracking the streaming task for `request_id` once it has ended.
+    pub fn release_stream(&self, request_id: &str) {
+        self.release(request_id, |entry| entry.stream = None);
+    }
+
+    /// Aborts the request and any stream spawned for it.
+    /// Returns `false` if nothing was in flight under `request_id`.
+    pub fn cancel(&self, request_id: &str) -> bool {
+        let entry = self.entries.lock().unwrap().remove(request_id);
+        match entry {
+            Some(entry) => {
+                if let Some(handle) = entry.request {
+                    handle.abort();
+                }
+                if let Some((_, handle)) = entry.stream {
+                    handle.abort();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Aborts the stream emitting on `channel_id`, leaving its request alone.
+    /// Returns `false` if no such stream is running.
+    pub fn close_stream(&self, channel_id: &str) -> bool {
+        let entries = self.entries.lock().unwrap();
+        let stream = entries
+            .values()
+            .filter_map(|entry| entry.stream.as_ref())
+            .find(|(channel, _)| channel == channel_id);
+        match stream {
+            Some((_, handle)) => {
+                handle.abort();
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn release(&self, request_id: &str, clear: impl FnOnce(&mut InFlightEntry)) {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(entry) = entries.get_mut(request_id) {
+            clear(entry);
+            if entry.is_empty() {
+                entries.remove(request_id);
+            }
+        }
+    }
+}
@@ -1,14 +1,53 @@
 use tauri::Manager;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::Instant;
-use futures_util::StreamExt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use uuid::Uuid;
 
 mod auth;
+mod batch;
+mod body;
+mod client_pool;
+mod compression;
+mod cookies;
+mod environments;
+mod errors;
+mod graphql;
+mod grpc;
+mod inflight;
+mod load_test;
+mod preferences;
+mod proxy;
+mod redirect;
+mod request_body;
+mod retry;
+mod sse;
+mod stream;
+mod template;
+mod timings;
+mod tls;
+mod websocket;
+
+use client_pool::{ClientPool, ClientSettings, CosmoHttpVersion, RequestClients};
+use compression::BodyDecoder;
+use cookies::CookieJars;
+use environments::CosmoResolvedRequest;
+use graphql::CosmoGraphQLResult;
+use preferences::WorkspaceScope;
+use inflight::InFlightRequests;
+use load_test::LoadTests;
+use proxy::CosmoProxy;
+use redirect::{CosmoRedirect, CosmoRedirectPolicy};
+use request_body::CosmoBody;
+use retry::{CosmoAttempt, CosmoRetryPolicy};
+use stream::{CosmoStreamRule, StreamFraming};
+use timings::{ConnectionTrace, CosmoTimings};
+use tls::CosmoTlsSettings;
+use websocket::WebSocketSessions;
 
 /// Represents an HTTP request sent from the frontend.
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct CosmoRequest {
     /// HTTP method (GET, POST, etc.)
     method: String,
@@ -16,19 +55,115 @@ pub struct CosmoRequest {
     url: String,
     /// Optional HTTP headers
     headers: Option<HashMap<String, String>>,
-    /// Optional request body
-    body: Option<String>,
+    /// Optional request body; a bare string is sent as raw text
+    #[serde(default, deserialize_with = "request_body::deserialize_body")]
+    body: Option<CosmoBody>,
+    /// Optional timeout limits; unset values fall back to workspace defaults
+    #[serde(default)]
+    timeouts: Option<CosmoTimeouts>,
+    /// Caller-supplied id used to cancel the request; generated when absent
+    #[serde(default)]
+    request_id: Option<String>,
+    /// Proxy for this request; overrides the workspace proxy when set
+    #[serde(default)]
+    proxy: Option<CosmoProxy>,
+    /// Redirect handling; follows up to 10 redirects when absent
+    #[serde(default)]
+    redirect: Option<CosmoRedirectPolicy>,
+    /// Channel to emit stream messages on, so the caller can subscribe before
+    /// sending; generated when absent
+    #[serde(default)]
+    stream_channel_id: Option<String>,
+    /// Stream the response with this framing whatever its content type
+    #[serde(default)]
+    stream: Option<StreamFraming>,
+    /// HTTP version negotiation; prefers HTTP/2 over ALPN when absent
+    #[serde(default)]
+    http_version: Option<CosmoHttpVersion>,
+    /// Return the body exactly as received, without undoing its `Content-Encoding`
+    #[serde(default)]
+    raw_body: bool,
+    /// Retry transient failures; each attempt gets the full timeouts
+    #[serde(default)]
+    retry: Option<CosmoRetryPolicy>,
+    /// Environment whose variables are substituted; the workspace's active
+    /// environment when absent
+    #[serde(default)]
+    environment_id: Option<String>,
+    /// Collection the request belongs to, whose variables are substituted
+    #[serde(default)]
+    collection_id: Option<String>,
+    /// Values for `{{name}}` placeholders in the URL, headers and body; these
+    /// override the workspace's environment, collection and global variables
+    #[serde(default)]
+    variables: HashMap<String, String>,
+    /// Seed for the random values of `{{$helpers}}`, so a run can be
+    /// reproduced; random when absent
+    #[serde(default)]
+    template_seed: Option<u64>,
+    /// Workspace rules for streaming by content type, set by the command
+    #[serde(skip)]
+    stream_rules: Vec<CosmoStreamRule>,
+    /// Workspace the request is sent from, set by the command rather than the
+    /// frontend; selects the cookie jar
+    #[serde(skip)]
+    workspace: Option<WorkspaceScope>,
+    /// TLS settings of the workspace, likewise set by the command
+    #[serde(skip)]
+    tls: CosmoTlsSettings,
+}
+
+/// Timeout limits for a single request, in milliseconds.
+///
+/// `connect_ms` bounds establishing the connection, `read_ms` bounds each read
+/// from the socket, and `total_ms` bounds the whole exchange including the body.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoTimeouts {
+    pub connect_ms: Option<u64>,
+    pub read_ms: Option<u64>,
+    pub total_ms: Option<u64>,
+}
+
+impl CosmoTimeouts {
+    /// Returns these limits with any unset value taken from `defaults`.
+    pub fn or(self, defaults: CosmoTimeouts) -> CosmoTimeouts {
+        CosmoTimeouts {
+            connect_ms: self.connect_ms.or(defaults.connect_ms),
+            read_ms: self.read_ms.or(defaults.read_ms),
+            total_ms: self.total_ms.or(defaults.total_ms),
+        }
+    }
 }
 
 /// Categorizes different types of failures that can occur during request execution.
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum CosmoErrorType {
     NetworkError,
     TimeoutError,
     DnsError,
     SslError,
     InvalidUrl,
+    Cancelled,
+    ProxyError,
     UnknownError,
+    /// Nothing was listening on the target port
+    ConnectionRefused,
+    /// The peer reset or closed the connection mid-exchange
+    ConnectionReset,
+    /// The server's certificate isn't trusted, e.g. signed by an unknown CA
+    CertificateInvalid,
+    /// The server's certificate is past its validity period
+    CertificateExpired,
+    /// The server's certificate isn't valid for the requested host
+    HostnameMismatch,
+    /// The redirect limit was reached
+    TooManyRedirects,
+    /// The response body couldn't be decoded, e.g. corrupt gzip
+    BodyDecodeError,
+    /// The request is invalid as given, e.g. it uses an undefined variable,
+    /// a malformed header or a request id that is already in flight
+    ValidationError,
 }
 
 /// Structured error returned to the frontend when a request fails.
@@ -36,6 +171,99 @@ pub enum CosmoErrorType {
 pub struct CosmoError {
     pub error_type: CosmoErrorType,
     pub message: String,
+    /// Underlying causes, outermost first, e.g. an I/O error and the TLS
+    /// alert behind it
+    pub causes: Vec<String>,
+    /// Every attempt made under the request's retry policy, in order; empty
+    /// without a policy
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attempts: Vec<CosmoAttempt>,
+    /// The request as sent, with its variables substituted, when the failure
+    /// came after substitution
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_request: Option<Box<CosmoResolvedRequest>>,
+}
+
+impl CosmoError {
+    /// Builds an error without underlying causes.
+    fn new(error_type: CosmoErrorType, message: impl Into<String>) -> CosmoError {
+        CosmoError {
+            error_type,
+            message: message.into(),
+            causes: Vec::new(),
+            attempts: Vec::new(),
+            resolved_request: None,
+        }
+    }
+
+    /// Builds an error with its own message, classified by the source chain of
+    /// `error` (falling back to `fallback`) and carrying its causes.
+    fn caused_by(fallback: CosmoErrorType, message: impl Into<String>, error: &(dyn std::error::Error + 'static)) -> CosmoError {
+        CosmoError {
+            causes: errors::causes(error),
+            ..CosmoError::new(errors::classify(error).unwrap_or(fallback), message)
+        }
+    }
+
+    /// Builds a `TimeoutError` naming the phase that exceeded its limit.
+    fn timeout(phase: &str, limit_ms: Option<u64>) -> CosmoError {
+        let message = match limit_ms {
+            Some(ms) => format!("Request timed out during {} phase (limit: {} ms)", phase, ms),
+            None => format!("Request timed out during {} phase", phase),
+        };
+        CosmoError::new(CosmoErrorType::TimeoutError, message)
+    }
+
+    /// Builds an error from a failure, classified by its source chain and
+    /// falling back to `fallback` when no cause is recognized.
+    fn from_source(fallback: CosmoErrorType, error: &(dyn std::error::Error + 'static)) -> CosmoError {
+        CosmoError::caused_by(fallback, error_chain(error), error)
+    }
+
+    /// Builds the error returned when a request is aborted by `cancel_cosmo_request`.
+    fn cancelled(request_id: &str) -> CosmoError {
+        CosmoError::new(CosmoErrorType::Cancelled, format!("Request {} was cancelled", request_id))
+    }
+}
+
+/// A single response header line.
+///
+/// Names are reported as the HTTP client exposes them, which is lowercase.
+/// Repeated headers such as `Set-Cookie` appear once per occurrence.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CosmoHeader {
+    pub name: String,
+    /// Header value, lossily decoded when it isn't valid UTF-8
+    pub value: String,
+    /// Exact value bytes, base64 encoded; only set when `value` is lossy
+    pub value_base64: Option<String>,
+}
+
+impl CosmoHeader {
+    fn from_pair(name: &reqwest::header::HeaderName, value: &reqwest::header::HeaderValue) -> CosmoHeader {
+        use base64::Engine;
+        let bytes = value.as_bytes();
+        let (value, value_base64) = match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (
+                String::from_utf8_lossy(bytes).into_owned(),
+                Some(base64::engine::general_purpose::STANDARD.encode(bytes)),
+            ),
+        };
+        CosmoHeader {
+            name: name.as_str().to_string(),
+            value,
+            value_base64,
+        }
+    }
+}
+
+/// Returns the first value of a header, matching the name case-insensitively.
+pub fn find_header<'a>(headers: &'a [CosmoHeader], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|h| h.name.eq_ignore_ascii_case(name))
+        .map(|h| h.value.as_str())
 }
 
 /// Successful HTTP response details.
@@ -43,147 +271,574 @@ pub struct CosmoError {
 pub struct CosmoResponse {
     /// HTTP status code (e.g., 200, 404)
     pub status: u16,
-    /// Raw response body
+    /// URL of the final response, after any redirects
+    pub url: String,
+    /// Redirects followed to reach the final response, in order
+    pub redirects: Vec<CosmoRedirect>,
+    /// Response body decoded as text; empty for binary payloads
     pub body: String,
-    /// Response headers
-    pub headers: HashMap<String, String>,
-    /// Request duration in milliseconds
+    /// Byte-exact response body, base64 encoded
+    pub body_base64: String,
+    /// MIME type from the `Content-Type` header, without parameters
+    pub mime_type: Option<String>,
+    /// Charset used to decode `body`, if the payload is textual
+    pub charset: Option<String>,
+    /// Whether the payload is binary and `body` was left empty
+    pub is_binary: bool,
+    /// Response headers with lowercase names, one entry per occurrence. Names
+    /// come in order of first appearance, with repeated values grouped under
+    /// their name in received order.
+    pub headers: Vec<CosmoHeader>,
+    /// Total wall time in milliseconds, including reading the body
     pub duration_ms: u128,
+    /// Breakdown of where the time went
+    pub timings: CosmoTimings,
+    /// Whether a new connection was opened, as opposed to reusing a pooled one
+    pub fresh_connection: bool,
     /// Whether this response is a stream
     pub is_stream: bool,
-    /// Channel ID for Server-Sent Events stream
+    /// How the stream's body is split into messages
+    pub stream_framing: Option<StreamFraming>,
+    /// Channel the stream's `StreamMessage`s are emitted on
     pub stream_channel_id: Option<String>,
+    /// Id the request was tracked under, usable with `cancel_cosmo_request`
+    pub request_id: Option<String>,
+    /// `data` and `errors` of a GraphQL operation's response, when the request
+    /// had a GraphQL body and the response is a GraphQL result
+    pub graphql: Option<CosmoGraphQLResult>,
+    /// HTTP version the response was received over, e.g. `HTTP/1.1` or `HTTP/2`
+    pub http_version: String,
+    /// TLS protocol version of the connection, e.g. `TLSv1.3`; `None` for plain HTTP
+    pub tls_version: Option<String>,
+    /// Negotiated cipher suite, e.g. `TLS13_AES_128_GCM_SHA256`
+    pub cipher_suite: Option<String>,
+    /// Body size as received, before undoing its content encoding; 0 for
+    /// streams, whose body is read afterwards
+    pub compressed_size: u64,
+    /// Body size after undoing its content encoding
+    pub decoded_size: u64,
+    /// Size of the status line and headers as HTTP/1.1 text; HTTP/2 compresses
+    /// headers on the wire, so this is their size before compression
+    pub header_size: u64,
+    /// Content codings that were undone, e.g. `gzip`; `None` when the body was
+    /// not encoded or `raw_body` was set
+    pub content_encoding: Option<String>,
+    /// Every attempt made under the request's retry policy, in order; empty
+    /// without a policy
+    pub attempts: Vec<CosmoAttempt>,
+    /// The request as sent, with its variables substituted
+    pub resolved_request: Option<CosmoResolvedRequest>,
 }
 
 /// Executes an HTTP request using reqwest.
 /// Handles normalization, client initialization, and execution timing.
+/// When a user and workspace are given, the workspace preferences supply
+/// defaults for any request settings left unset and the workspace's cookie
+/// jar and TLS settings are applied. The execution is tracked under
+/// its request id until it completes so it can be cancelled.
+#[tauri::command]
+async fn execute_cosmo_request(
+    app_handle: tauri::AppHandle,
+    in_flight: tauri::State<'_, InFlightRequests>,
+    request: CosmoRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<CosmoResponse, CosmoError> {
+    let mut request = request;
+    apply_workspace_preferences(&app_handle, &mut request, user_id, workspace_id).await;
+    let request_id = request
+        .request_id
+        .get_or_insert_with(|| Uuid::new_v4().to_string())
+        .clone();
+    in_flight.run(&request_id, do_execute_cosmo_request(Some(app_handle), request)).await
+}
+
+/// Runs the standard introspection query against a GraphQL endpoint and
+/// returns a summary of its schema (types, fields and arguments) for
+/// autocompletion. The request's URL, headers and settings are used as for
+/// `execute_cosmo_request`; its method and body are replaced.
+#[tauri::command]
+async fn introspect_graphql_schema(
+    app_handle: tauri::AppHandle,
+    request: CosmoRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<graphql::GraphQLSchemaSummary, CosmoError> {
+    let mut request = request;
+    apply_workspace_preferences(&app_handle, &mut request, user_id, workspace_id).await;
+    graphql::introspect(Some(app_handle), request).await
+}
+
+/// Applies the workspace preferences and variables to a single request; see
+/// `WorkspaceContext::apply`.
+async fn apply_workspace_preferences(
+    app_handle: &tauri::AppHandle,
+    request: &mut CosmoRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) {
+    if let Some(context) = WorkspaceContext::load(app_handle, user_id, workspace_id).await {
+        context.apply(request);
+    }
+}
+
+/// A workspace's preferences and environments, read once per command and
+/// applied to each of its requests.
+struct WorkspaceContext {
+    scope: WorkspaceScope,
+    preferences: preferences::WorkspacePreferences,
+    environments: environments::WorkspaceEnvironments,
+}
+
+impl WorkspaceContext {
+    /// Reads the workspace's files on a blocking thread. Returns `None` unless
+    /// both a user and a workspace are given.
+    async fn load(app_handle: &tauri::AppHandle, user_id: Option<String>, workspace_id: Option<String>) -> Option<WorkspaceContext> {
+        let scope = WorkspaceScope::new(user_id?, workspace_id?);
+        let app_handle = app_handle.clone();
+        let load = move || WorkspaceContext {
+            preferences: preferences::load_workspace_preferences(&app_handle, &scope.user_id, &scope.workspace_id),
+            environments: environments::load_environments(&app_handle, &scope),
+            scope,
+        };
+        match tokio::task::spawn_blocking(load).await {
+            Ok(context) => Some(context),
+            Err(e) => {
+                log::warn!("Failed to load workspace settings: {}", e);
+                None
+            }
+        }
+    }
+
+    /// Fills in settings the request left unset from the workspace
+    /// preferences, scopes it to the workspace's cookie jar and TLS settings,
+    /// and brings the workspace's variables into scope beneath the request's own.
+    fn apply(&self, request: &mut CosmoRequest) {
+        let prefs = &self.preferences;
+        request.timeouts = Some(request.timeouts.unwrap_or_default().or(prefs.request_timeouts));
+        request.proxy = request.proxy.take().or_else(|| prefs.proxy.clone());
+        request.tls = prefs.tls.clone();
+        request.stream_rules = prefs.stream_rules.clone();
+        let variables = self
+            .environments
+            .variables(request.environment_id.as_deref(), request.collection_id.as_deref());
+        for (key, value) in variables {
+            request.variables.entry(key).or_insert(value);
+        }
+        request.workspace = Some(self.scope.clone());
+    }
+}
+
+/// Cancels an in-flight request and any stream it spawned.
+/// Returns `false` if no request with this id is running.
+#[tauri::command]
+async fn cancel_cosmo_request(
+    in_flight: tauri::State<'_, InFlightRequests>,
+    request_id: String,
+) -> Result<bool, String> {
+    Ok(in_flight.cancel(&request_id))
+}
+
+/// Stops reading a response stream without cancelling anything else.
+/// Returns `false` if no stream is emitting on this channel.
 #[tauri::command]
-async fn execute_cosmo_request(app_handle: tauri::AppHandle, request: CosmoRequest) -> Result<CosmoResponse, CosmoError> {
-    do_execute_cosmo_request(Some(app_handle), request).await
+async fn close_cosmo_stream(
+    in_flight: tauri::State<'_, InFlightRequests>,
+    stream_channel_id: String,
+) -> Result<bool, String> {
+    Ok(in_flight.close_stream(&stream_channel_id))
 }
 
+/// Substitutes the request's variables, then executes it. The resolved
+/// request is echoed on the response, or on the error if sending failed.
 async fn do_execute_cosmo_request(app_handle: Option<tauri::AppHandle>, request: CosmoRequest) -> Result<CosmoResponse, CosmoError> {
-    let client = reqwest::Client::builder()
-        .user_agent("Cosmonaut/1.0 (Desktop API Client)")
-        .build()
-        .map_err(|e| CosmoError {
-            error_type: CosmoErrorType::UnknownError,
-            message: format!("Failed to initialize HTTP client: {}", e),
-        })?;
-        
-    let start = Instant::now();
+    let mut request = request;
+    let resolved = environments::resolve_request(&mut request)?;
+    match execute_resolved_request(app_handle, request).await {
+        Ok(mut response) => {
+            response.resolved_request = Some(resolved);
+            Ok(response)
+        }
+        Err(mut error) => {
+            error.resolved_request = Some(Box::new(resolved));
+            Err(error)
+        }
+    }
+}
 
-    let method = match request.method.to_uppercase().as_str() {
-        "GET" => reqwest::Method::GET,
-        "POST" => reqwest::Method::POST,
-        "PUT" => reqwest::Method::PUT,
-        "DELETE" => reqwest::Method::DELETE,
-        "PATCH" => reqwest::Method::PATCH,
-        _ => return Err(CosmoError {
-            error_type: CosmoErrorType::UnknownError,
-            message: format!("Unsupported method: {}", request.method),
-        }),
+/// Executes a request with a client from the app's shared pool, or a
+/// dedicated client when running without an app handle.
+async fn execute_resolved_request(app_handle: Option<tauri::AppHandle>, request: CosmoRequest) -> Result<CosmoResponse, CosmoError> {
+    let timeouts = request.timeouts.unwrap_or_default();
+    let cookie_jar = match (&app_handle, &request.workspace) {
+        (Some(handle), Some(scope)) => match handle.try_state::<CookieJars>() {
+            Some(jars) => Some(jars.jar(handle, scope).await.map_err(|e| CosmoError::new(
+                CosmoErrorType::UnknownError,
+                format!("Failed to open cookie jar: {}", e),
+            ))?),
+            None => None,
+        },
+        _ => None,
+    };
+    let settings = ClientSettings {
+        connect_timeout_ms: timeouts.connect_ms,
+        read_timeout_ms: timeouts.read_ms,
+        cookie_scope: cookie_jar.as_ref().and(request.workspace.clone()),
+        proxy: request.proxy.clone(),
+        tls: request.tls.clone(),
+        http_version: request.http_version.unwrap_or_default(),
     };
+    let clients = RequestClients::new(app_handle.clone(), settings, cookie_jar);
+
+    match request.retry.clone() {
+        Some(policy) => {
+            let method = request.method.clone();
+            retry::send_with_retries(&policy, &method, || {
+                send_attempt(app_handle.clone(), clients.clone(), request.clone(), timeouts)
+            })
+            .await
+        }
+        None => send_attempt(app_handle, clients, request, timeouts).await,
+    }
+}
+
+/// Sends the request once, within its total timeout.
+async fn send_attempt(
+    app_handle: Option<tauri::AppHandle>,
+    clients: RequestClients,
+    request: CosmoRequest,
+    timeouts: CosmoTimeouts,
+) -> Result<CosmoResponse, CosmoError> {
+    match timeouts.total_ms {
+        Some(ms) => tokio::time::timeout(Duration::from_millis(ms), send_cosmo_request(app_handle, clients, request, timeouts))
+            .await
+            .unwrap_or_else(|_| Err(CosmoError::timeout("total", Some(ms)))),
+        None => send_cosmo_request(app_handle, clients, request, timeouts).await,
+    }
+}
+
+async fn send_cosmo_request(
+    app_handle: Option<tauri::AppHandle>,
+    clients: RequestClients,
+    request: CosmoRequest,
+    timeouts: CosmoTimeouts,
+) -> Result<CosmoResponse, CosmoError> {
+    let request_id = request.request_id.clone();
+    let is_graphql = matches!(request.body, Some(CosmoBody::Graphql { .. }));
+    let start = Instant::now();
 
-    let mut rb = client.request(method.clone(), &request.url);
+    let method = parse_method(&request.method)?;
 
-    if let Err(e) = reqwest::Url::parse(&request.url) {
-        return Err(CosmoError {
-            error_type: CosmoErrorType::InvalidUrl,
-            message: format!("Invalid URL format: {}. Please ensure the protocol (http/https) is correct.", e),
-        });
+    let mut url = reqwest::Url::parse(&request.url).map_err(|e| CosmoError::new(
+        CosmoErrorType::InvalidUrl,
+        format!("Invalid URL format: {}. Please ensure the protocol (http/https) is correct.", e),
+    ))?;
+    let mut method = method;
+    let mut request_headers: Vec<(String, String)> = request.headers.into_iter().flatten().collect();
+    let mut request_body = request.body.as_ref();
+    if let Some(body) = request_body {
+        let has_content_type = request_headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type"));
+        if body.owns_content_type() {
+            request_headers.retain(|(name, _)| !name.eq_ignore_ascii_case("content-type"));
+        }
+        if let Some(content_type) = body.content_type().filter(|_| !has_content_type) {
+            request_headers.push(("Content-Type".to_string(), content_type));
+        }
     }
+    if !request_headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("accept-encoding")) {
+        request_headers.push(("Accept-Encoding".to_string(), compression::ACCEPT_ENCODING.to_string()));
+    }
+    let policy = request.redirect.unwrap_or_default();
+    let mut redirects = Vec::new();
+    let mut client = clients.for_url(&url).await?;
 
-    if let Some(headers) = request.headers {
-        for (key, value) in headers {
+    let trace = Arc::new(ConnectionTrace::default());
+    let response = loop {
+        let mut rb = client.client.request(method.clone(), url.clone());
+        for (key, value) in &request_headers {
             rb = rb.header(key, value);
         }
-    }
+        if let Some(body) = request_body {
+            rb = body.attach(rb).await?;
+        }
 
-    if let Some(body) = request.body {
-        rb = rb.body(body);
-    }
+        let proxied = request
+            .proxy
+            .as_ref()
+            .filter(|proxy| proxy.applies_to(&url))
+            .map(|proxy| proxy.redacted_url());
+        let response = trace
+            .clone()
+            .scope(rb.send())
+            .await
+            .map_err(|e| send_error(e, timeouts, proxied.as_deref()))?;
+        if let Some(negotiated) = trace.take_negotiated_tls() {
+            client.remember_negotiated(&url, negotiated);
+        }
 
-    let response = rb.send().await.map_err(|e| {
-        let error_type = if e.is_timeout() {
-            CosmoErrorType::TimeoutError
-        } else if e.is_connect() {
-            CosmoErrorType::NetworkError
-        } else if e.to_string().contains("dns") {
-            CosmoErrorType::DnsError
-        } else if e.is_request() && e.to_string().contains("ssl") {
-            CosmoErrorType::SslError
-        } else {
-            CosmoErrorType::NetworkError
+        if !policy.follow {
+            break response;
+        }
+        let location = response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|value| value.to_str().ok());
+        let hop = match redirect::next_hop(&url, &method, response.status(), location) {
+            Some(hop) => hop,
+            None => break response,
         };
+        if redirects.len() >= policy.max_redirects as usize {
+            return Err(CosmoError::new(
+                CosmoErrorType::TooManyRedirects,
+                format!("Too many redirects (limit: {}), last one to {}", policy.max_redirects, hop.url),
+            ));
+        }
 
-        CosmoError {
-            error_type,
-            message: e.to_string(),
+        redirects.push(CosmoRedirect {
+            url: url.to_string(),
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| CosmoHeader::from_pair(name, value))
+                .collect(),
+        });
+        if hop.drops_body {
+            request_body = None;
+            request_headers.retain(|(name, _)| !redirect::is_body_header(name));
+        }
+        if hop.crosses_origin && !policy.keep_auth_across_origins {
+            request_headers.retain(|(name, _)| !redirect::is_auth_header(name));
         }
-    })?;
-    let duration = start.elapsed().as_millis();
+        if hop.crosses_origin {
+            client = clients.for_url(&hop.url).await?;
+        }
+        method = hop.method;
+        url = hop.url;
+    };
+    let headers_at = Instant::now();
 
     let status = response.status().as_u16();
-    let mut headers = HashMap::new();
-    let mut is_stream = false;
-    for (name, value) in response.headers().iter() {
-        let name_str = name.to_string();
-        let val_str = value.to_str().unwrap_or("").to_string();
-        if name_str.to_lowercase() == "content-type" && val_str.contains("text/event-stream") {
-            is_stream = true;
-        }
-        headers.insert(name_str, val_str);
-    }
+    let http_version = version_name(response.version());
+    // Pooled connections don't handshake again, so look up what this
+    // client's connection to the origin negotiated when it was opened
+    let negotiated = Some(&url).filter(|url| url.scheme() == "https").and_then(|url| client.negotiated_for(url));
+    let (tls_version, cipher_suite) = match negotiated {
+        Some(negotiated) => (Some(negotiated.version.to_string()), Some(negotiated.cipher_suite)),
+        None => (None, None),
+    };
+    // HEAD responses never carry a body, whatever their headers advertise
+    let has_body = method != reqwest::Method::HEAD;
+    let headers: Vec<CosmoHeader> = response
+        .headers()
+        .iter()
+        .map(|(name, value)| CosmoHeader::from_pair(name, value))
+        .collect();
+    let header_size = header_block_size(response.status(), response.headers());
+    let encoding_header = find_header(&headers, "content-encoding");
+    let decoder = BodyDecoder::for_content_encoding(encoding_header).filter(|_| !request.raw_body);
+    let content_encoding = decoder.as_ref().map(BodyDecoder::content_encoding);
+    // Raw bodies, and bodies in a coding we can't undo, stay encoded
+    let still_encoded = decoder.is_none() && encoding_header.is_some_and(|v| !v.trim().eq_ignore_ascii_case("identity"));
+    let stream_framing = request
+        .stream
+        .or_else(|| stream::framing_for(find_header(&headers, "content-type"), &request.stream_rules))
+        .filter(|_| has_body);
 
-    if is_stream {
-        let stream_channel_id = Uuid::new_v4().to_string();
-        let channel_id_clone = stream_channel_id.clone();
-        
+    if let Some(framing) = stream_framing {
+        let stream_channel_id = request.stream_channel_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
         if let Some(handle) = app_handle {
-            use tauri::Emitter;
-            tokio::spawn(async move {
-                let mut stream = response.bytes_stream();
-                while let Some(chunk_result) = stream.next().await {
-                    match chunk_result {
-                        Ok(bytes) => {
-                            let text = String::from_utf8_lossy(&bytes).to_string();
-                            let _ = handle.emit(&channel_id_clone, text);
-                        }
-                        Err(e) => {
-                            log::error!("Error reading stream chunk: {}", e);
-                            break;
-                        }
-                    }
-                }
-            });
+            stream::spawn_stream(handle, response, decoder, framing, stream_channel_id.clone(), request_id.clone());
         }
-        
+
+        let (mime_type, charset) = body::parse_content_type(find_header(&headers, "content-type").unwrap_or(""));
         return Ok(CosmoResponse {
             status,
+            url: url.to_string(),
+            redirects,
             body: "".to_string(),
+            body_base64: String::new(),
+            mime_type,
+            charset,
+            is_binary: false,
             headers,
-            duration_ms: duration,
+            duration_ms: headers_at.duration_since(start).as_millis(),
+            timings: CosmoTimings::new(start, headers_at, headers_at, &trace),
+            fresh_connection: trace.opened_connection(),
             is_stream: true,
+            stream_framing: Some(framing),
             stream_channel_id: Some(stream_channel_id),
+            request_id,
+            graphql: None,
+            http_version,
+            tls_version,
+            cipher_suite,
+            compressed_size: 0,
+            decoded_size: 0,
+            header_size,
+            content_encoding,
+            attempts: Vec::new(),
+            resolved_request: None,
+        });
+    }
+
+    if !has_body {
+        let (mime_type, charset) = body::parse_content_type(find_header(&headers, "content-type").unwrap_or(""));
+        return Ok(CosmoResponse {
+            status,
+            url: url.to_string(),
+            redirects,
+            body: String::new(),
+            body_base64: String::new(),
+            mime_type,
+            charset,
+            is_binary: false,
+            headers,
+            duration_ms: headers_at.duration_since(start).as_millis(),
+            timings: CosmoTimings::new(start, headers_at, headers_at, &trace),
+            fresh_connection: trace.opened_connection(),
+            is_stream: false,
+            stream_framing: None,
+            stream_channel_id: None,
+            request_id,
+            graphql: None,
+            http_version,
+            tls_version,
+            cipher_suite,
+            compressed_size: 0,
+            decoded_size: 0,
+            header_size,
+            content_encoding: None,
+            attempts: Vec::new(),
+            resolved_request: None,
         });
     }
 
-    let body = response.text().await.map_err(|e| CosmoError {
-        error_type: CosmoErrorType::UnknownError,
-        message: e.to_string(),
-    })?;
+    let bytes = response.bytes().await.map_err(|e| body_error(e, timeouts))?;
+    let compressed_size = bytes.len() as u64;
+    let bytes = match decoder {
+        Some(decoder) => decoder.decode(&bytes).map_err(|message| CosmoError::new(
+            CosmoErrorType::BodyDecodeError,
+            message,
+        ))?,
+        None => bytes.to_vec(),
+    };
+    let end = Instant::now();
+    let decoded = if still_encoded {
+        body::encoded_body(find_header(&headers, "content-type"), &bytes)
+    } else {
+        body::decode_body(find_header(&headers, "content-type"), &bytes)
+    };
+    let graphql = if is_graphql { graphql::parse_result(&decoded.text) } else { None };
 
     Ok(CosmoResponse {
         status,
-        body,
+        url: url.to_string(),
+        redirects,
+        body: decoded.text,
+        body_base64: decoded.base64,
+        mime_type: decoded.mime_type,
+        charset: decoded.charset,
+        is_binary: decoded.is_binary,
         headers,
-        duration_ms: duration,
+        duration_ms: end.duration_since(start).as_millis(),
+        timings: CosmoTimings::new(start, headers_at, end, &trace),
+        fresh_connection: trace.opened_connection(),
         is_stream: false,
+        stream_framing: None,
         stream_channel_id: None,
+        request_id,
+        graphql,
+        http_version,
+        tls_version,
+        cipher_suite,
+        compressed_size,
+        decoded_size: bytes.len() as u64,
+        header_size,
+        content_encoding,
+        attempts: Vec::new(),
+        resolved_request: None,
     })
 }
 
+/// Size of a response's status line and headers written out as HTTP/1.1.
+fn header_block_size(status: reqwest::StatusCode, headers: &reqwest::header::HeaderMap) -> u64 {
+    // "HTTP/1.1 200 OK\r\n"
+    let status_line = 9 + status.as_str().len() + status.canonical_reason().map_or(0, |reason| reason.len() + 1) + 2;
+    // "name: value\r\n" per field, then the blank line
+    let fields: usize = headers.iter().map(|(name, value)| name.as_str().len() + value.len() + 4).sum();
+    (status_line + fields + 2) as u64
+}
+
+fn version_name(version: reqwest::Version) -> String {
+    match version {
+        reqwest::Version::HTTP_09 => "HTTP/0.9",
+        reqwest::Version::HTTP_10 => "HTTP/1.0",
+        reqwest::Version::HTTP_2 => "HTTP/2",
+        reqwest::Version::HTTP_3 => "HTTP/3",
+        _ => "HTTP/1.1",
+    }
+    .to_string()
+}
+
+/// Maps a failure to send a request (or receive its headers) to a `CosmoError`.
+/// `proxy_url` is the proxy the request was routed through, if any.
+fn send_error(e: reqwest::Error, timeouts: CosmoTimeouts, proxy_url: Option<&str>) -> CosmoError {
+    if e.is_timeout() {
+        return if e.is_connect() {
+            CosmoError::timeout("connect", timeouts.connect_ms)
+        } else {
+            CosmoError::timeout("read", timeouts.read_ms)
+        };
+    }
+
+    if let (Some(proxy_url), true) = (proxy_url, e.is_connect()) {
+        let message = format!("Failed to connect through proxy {}: {}", proxy_url, error_chain(&e));
+        return CosmoError::caused_by(CosmoErrorType::ProxyError, message, &e);
+    }
+
+    CosmoError::from_source(CosmoErrorType::NetworkError, &e)
+}
+
+/// Maps a failure to read a response body to a `CosmoError`.
+fn body_error(e: reqwest::Error, timeouts: CosmoTimeouts) -> CosmoError {
+    if e.is_timeout() {
+        return CosmoError::timeout("read", timeouts.read_ms);
+    }
+    let fallback = if e.is_decode() { CosmoErrorType::BodyDecodeError } else { CosmoErrorType::NetworkError };
+    CosmoError::from_source(fallback, &e)
+}
+
+/// Formats an error followed by each of its underlying causes.
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(cause) = source {
+        message.push_str(": ");
+        message.push_str(&cause.to_string());
+        source = cause.source();
+    }
+    message
+}
+
+/// Parses an HTTP method token.
+///
+/// Standard methods are matched case-insensitively; any other valid token
+/// (e.g. `PROPFIND`) is passed through verbatim as an extension method.
+fn parse_method(method: &str) -> Result<reqwest::Method, CosmoError> {
+    let method = method.trim();
+    const STANDARD: [&str; 9] = ["GET", "POST", "PUT", "DELETE", "PATCH", "HEAD", "OPTIONS", "TRACE", "CONNECT"];
+    let token = match STANDARD.iter().find(|m| m.eq_ignore_ascii_case(method)) {
+        Some(standard) => standard,
+        None => method,
+    };
+
+    reqwest::Method::from_bytes(token.as_bytes()).map_err(|_| CosmoError::new(
+        CosmoErrorType::ValidationError,
+        format!("Invalid HTTP method: '{}'", method),
+    ))
+}
+
 /// Saves collection data to a JSON file scoped by user and workspace.
 #[tauri::command]
 async fn save_collections(
@@ -320,7 +975,73 @@ async fn load_user_preferences(
     std::fs::read_to_string(file_path).map_err(|e| e.to_string())
 }
 
+/// Saves workspace-level preferences (request engine defaults) to a JSON file.
+#[tauri::command]
+async fn save_workspace_preferences(
+    app_handle: tauri::AppHandle,
+    user_id: String,
+    workspace_id: String,
+    preferences: String
+) -> Result<(), String> {
+    let user_workspace_dir = preferences::workspace_dir(&app_handle, &user_id, &workspace_id)?;
+    std::fs::create_dir_all(&user_workspace_dir).map_err(|e| e.to_string())?;
+
+    let file_path = user_workspace_dir.join("preferences.json");
+    std::fs::write(file_path, preferences).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Loads workspace-level preferences from the local filesystem.
+#[tauri::command]
+async fn load_workspace_preferences(
+    app_handle: tauri::AppHandle,
+    user_id: String,
+    workspace_id: String
+) -> Result<String, String> {
+    let file_path = preferences::workspace_dir(&app_handle, &user_id, &workspace_id)?.join("preferences.json");
+
+    if !file_path.exists() {
+        return Ok("{}".to_string());
+    }
+
+    std::fs::read_to_string(file_path).map_err(|e| e.to_string())
+}
+
+
+
+/// Saves a workspace's environments and variables, beside its collections.
+#[tauri::command]
+async fn save_environments(
+    app_handle: tauri::AppHandle,
+    user_id: String,
+    workspace_id: String,
+    environments: String
+) -> Result<(), String> {
+    let user_workspace_dir = preferences::workspace_dir(&app_handle, &user_id, &workspace_id)?;
+    std::fs::create_dir_all(&user_workspace_dir).map_err(|e| e.to_string())?;
+
+    let file_path = user_workspace_dir.join("environments.json");
+    std::fs::write(file_path, environments).map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Loads a workspace's environments and variables from the local filesystem.
+#[tauri::command]
+async fn load_environments(
+    app_handle: tauri::AppHandle,
+    user_id: String,
+    workspace_id: String
+) -> Result<String, String> {
+    let file_path = preferences::workspace_dir(&app_handle, &user_id, &workspace_id)?.join("environments.json");
+
+    if !file_path.exists() {
+        return Ok("{}".to_string());
+    }
 
+    std::fs::read_to_string(file_path).map_err(|e| e.to_string())
+}
 
 /// Gets the current demo request count from secure local storage
 #[tauri::command]
@@ -361,7 +1082,6 @@ async fn increment_demo_request_count(app_handle: tauri::AppHandle) -> Result<u3
 }
 
 /// Entry point for the Tauri application.
-
 /// Configures handlers, plugins, and setup logic.
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
@@ -371,10 +1091,28 @@ pub fn run() {
     .plugin(tauri_plugin_updater::Builder::new().build())
     .plugin(tauri_plugin_notification::init())
     .plugin(tauri_plugin_process::init())
+    .manage(InFlightRequests::default())
+    .manage(ClientPool::default())
+    .manage(CookieJars::default())
+    .manage(WebSocketSessions::default())
+    .manage(LoadTests::default())
     .invoke_handler(tauri::generate_handler![
         execute_cosmo_request,
+        batch::execute_cosmo_batch,
+        load_test::start_load_test,
+        load_test::stop_load_test,
+        cancel_cosmo_request,
+        close_cosmo_stream,
+        introspect_graphql_schema,
+        grpc::execute_grpc_request,
+        grpc::describe_grpc_services,
+        websocket::open_websocket,
+        websocket::send_websocket_message,
+        websocket::close_websocket,
         save_collections,
         load_collections,
+        save_environments,
+        load_environments,
         save_workspaces,
         load_workspaces,
         save_history,
@@ -386,6 +1124,12 @@ pub fn run() {
         auth::token::refresh_token,
         save_user_preferences,
         load_user_preferences,
+        save_workspace_preferences,
+        load_workspace_preferences,
+        cookies::list_cookies,
+        cookies::save_cookie,
+        cookies::delete_cookie,
+        cookies::clear_cookies,
         get_demo_request_count,
         increment_demo_request_count,
     ])
@@ -422,6 +1166,7 @@ mod tests {
             url: format!("{}/test", server.url()),
             headers: None,
             body: None,
+            ..Default::default()
         };
 
         let result = do_execute_cosmo_request(None, request).await;
@@ -445,7 +1190,8 @@ mod tests {
             headers: Some(HashMap::from([
                 ("Content-Type".to_string(), "application/json".to_string())
             ])),
-            body: Some(r#"{"data": 123}"#.to_string()),
+            body: Some(r#"{"data": 123}"#.to_string().into()),
+            ..Default::default()
         };
 
         let result = do_execute_cosmo_request(None, request).await;
@@ -462,14 +1208,2217 @@ mod tests {
             url: "ht tp://invalid-url".to_string(),
             headers: None,
             body: None,
+            ..Default::default()
         };
 
         let result = do_execute_cosmo_request(None, request).await;
         assert!(result.is_err());
         let error = result.unwrap_err();
-        match error.error_type {
-             CosmoErrorType::InvalidUrl => assert!(true),
-             _ => assert!(false, "Expected InvalidUrl error type"),
+        assert!(
+            matches!(error.error_type, CosmoErrorType::InvalidUrl),
+            "Expected InvalidUrl error type"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_head_request_has_no_body() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("HEAD", "/health")
+            .with_status(200)
+            .with_header("content-length", "42")
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "head".to_string(),
+            url: format!("{}/health", server.url()),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "");
+        assert_eq!(find_header(&response.headers, "Content-Length"), Some("42"));
+    }
+
+    #[tokio::test]
+    async fn test_extension_methods() {
+        let mut server = Server::new_async().await;
+        let _options = server.mock("OPTIONS", "/resource")
+            .with_status(204)
+            .with_header("access-control-allow-methods", "GET, PROPFIND")
+            .create_async().await;
+        let _propfind = server.mock("PROPFIND", "/resource")
+            .with_status(207)
+            .with_body("<multistatus/>")
+            .create_async().await;
+
+        let preflight = CosmoRequest {
+            method: "OPTIONS".to_string(),
+            url: format!("{}/resource", server.url()),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, preflight).await.unwrap();
+        assert_eq!(response.status, 204);
+
+        let propfind = CosmoRequest {
+            method: "PROPFIND".to_string(),
+            url: format!("{}/resource", server.url()),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, propfind).await.unwrap();
+        assert_eq!(response.status, 207);
+        assert_eq!(response.body, "<multistatus/>");
+    }
+
+    #[tokio::test]
+    async fn test_binary_body_is_byte_exact() {
+        let png: Vec<u8> = vec![0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff];
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/logo.png")
+            .with_status(200)
+            .with_header("content-type", "image/png")
+            .with_body(png.clone())
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/logo.png", server.url()),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        use base64::Engine;
+        let decoded = base64::engine::general_purpose::STANDARD.decode(&response.body_base64).unwrap();
+        assert_eq!(decoded, png);
+        assert!(response.is_binary);
+        assert_eq!(response.body, "");
+        assert_eq!(response.mime_type.as_deref(), Some("image/png"));
+    }
+
+    #[tokio::test]
+    async fn test_text_body_uses_declared_charset() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/latin1")
+            .with_status(200)
+            .with_header("content-type", "text/plain; charset=ISO-8859-1")
+            .with_body(vec![b'c', b'a', b'f', 0xe9])
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/latin1", server.url()),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.body, "café");
+        assert!(!response.is_binary);
+        assert_eq!(response.mime_type.as_deref(), Some("text/plain"));
+        assert_eq!(response.charset.as_deref(), Some("windows-1252"));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_headers_are_preserved() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/login")
+            .with_status(200)
+            .with_header("set-cookie", "session=abc; Path=/")
+            .with_header("set-cookie", "theme=dark; Path=/")
+            .with_header("x-legacy", "caf\u{e9}")
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/login", server.url()),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        let cookies: Vec<&str> = response.headers
+            .iter()
+            .filter(|h| h.name == "set-cookie")
+            .map(|h| h.value.as_str())
+            .collect();
+        assert_eq!(cookies, vec!["session=abc; Path=/", "theme=dark; Path=/"]);
+        assert!(response.headers.iter().all(|h| h.value_base64.is_none()));
+    }
+
+    /// reqwest reads the response head with hyper, which lowercases names and
+    /// stores them in a `HeaderMap` that groups values by name. hyper's record
+    /// of the received casing is private to it and reqwest has no switch for
+    /// it, so the raw casing and interleaving of lines are not recoverable
+    /// without replacing the HTTP client. This pins down what is kept.
+    #[tokio::test]
+    async fn test_header_case_and_order_as_parsed() {
+        let url = reply_server(|_| Some(concat!(
+            "HTTP/1.1 200 OK\r\n",
+            "X-Request-ID: 1\r\n",
+            "Set-Cookie: a=1\r\n",
+            "Vary: Accept\r\n",
+            "Set-Cookie: b=2\r\n",
+            "Content-Length: 0\r\n",
+            "Connection: close\r\n\r\n",
+        ).to_string())).await;
+
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            ..Default::default()
+        }).await.unwrap();
+        let lines: Vec<(&str, &str)> = response.headers.iter().map(|h| (h.name.as_str(), h.value.as_str())).collect();
+        assert_eq!(lines, vec![
+            ("x-request-id", "1"),
+            ("set-cookie", "a=1"),
+            ("set-cookie", "b=2"),
+            ("vary", "Accept"),
+            ("content-length", "0"),
+            ("connection", "close"),
+        ]);
+    }
+
+    #[test]
+    fn test_non_utf8_header_value_is_lossless() {
+        use base64::Engine;
+        let name = reqwest::header::HeaderName::from_static("x-legacy");
+        let value = reqwest::header::HeaderValue::from_bytes(&[b'c', b'a', b'f', 0xe9]).unwrap();
+
+        let header = CosmoHeader::from_pair(&name, &value);
+        assert_eq!(header.value, "caf\u{fffd}");
+        let raw = base64::engine::general_purpose::STANDARD.decode(header.value_base64.unwrap()).unwrap();
+        assert_eq!(raw, vec![b'c', b'a', b'f', 0xe9]);
+    }
+
+    #[tokio::test]
+    async fn test_timings_cover_connection_and_body() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/slow-body")
+            .with_status(200)
+            .with_chunked_body(|w| {
+                w.write_all(b"first")?;
+                std::thread::sleep(Duration::from_millis(150));
+                w.write_all(b"second")
+            })
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/slow-body", server.url()).replace("127.0.0.1", "localhost"),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        let timings = &response.timings;
+        assert_eq!(response.body, "firstsecond");
+        assert!(timings.dns_ms.is_some(), "expected a DNS lookup for localhost");
+        assert!(timings.tcp_ms.is_some());
+        assert_eq!(timings.tls_ms, None);
+        assert!(timings.download_ms >= 100.0, "download was {} ms", timings.download_ms);
+        assert!(timings.total_ms >= timings.ttfb_ms + timings.download_ms);
+        assert!(response.duration_ms >= 150);
+    }
+
+    /// Answers every request with `pong`, keeping connections alive.
+    /// (mockito closes the connection after each response.)
+    async fn keep_alive_server() -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let counter = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    while let Ok(n) = socket.read(&mut buf).await {
+                        if n == 0 {
+                            break;
+                        }
+                        let reply = b"HTTP/1.1 200 OK\r\ncontent-length: 4\r\n\r\npong";
+                        if socket.write_all(reply).await.is_err() {
+                            break;
+                        }
+                    }
+                });
+            }
+        });
+        (format!("http://{}", addr), accepted)
+    }
+
+    #[tokio::test]
+    async fn test_pooled_client_reuses_connection() {
+        let (url, accepted) = keep_alive_server().await;
+
+        let clients = RequestClients::new(None, ClientSettings::default(), None);
+        let mut fresh = Vec::new();
+        for _ in 0..2 {
+            let request = CosmoRequest {
+                method: "GET".to_string(),
+                url: format!("{}/ping", url),
+                ..Default::default()
+            };
+            let response = send_cosmo_request(None, clients.clone(), request, CosmoTimeouts::default()).await.unwrap();
+            assert_eq!(response.body, "pong");
+            fresh.push(response.fresh_connection);
         }
+
+        assert_eq!(fresh, vec![true, false]);
+        assert_eq!(accepted.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cookie_jar_round_trip() {
+        let mut server = Server::new_async().await;
+        let _login = server.mock("POST", "/login")
+            .with_status(200)
+            .with_header("set-cookie", "session=abc123; Path=/; HttpOnly")
+            .create_async().await;
+        let profile = server.mock("GET", "/profile")
+            .match_header("cookie", "session=abc123")
+            .with_status(200)
+            .with_body("welcome")
+            .create_async().await;
+
+        let jar = Arc::new(cookies::CookieJar::in_memory());
+        let clients = RequestClients::new(None, ClientSettings::default(), Some(jar.clone()));
+        for (method, path) in [("POST", "/login"), ("GET", "/profile")] {
+            let request = CosmoRequest {
+                method: method.to_string(),
+                url: format!("{}{}", server.url(), path),
+                ..Default::default()
+            };
+            send_cosmo_request(None, clients.clone(), request, CosmoTimeouts::default()).await.unwrap();
+        }
+        profile.assert_async().await;
+
+        let stored = jar.list(Some("127.0.0.1"));
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].name, "session");
+        assert!(stored[0].http_only);
+
+        let mut edited = stored[0].clone();
+        edited.value = "changed".to_string();
+        jar.upsert(&edited).unwrap();
+        assert_eq!(jar.list(None)[0].value, "changed");
+
+        assert!(jar.remove("127.0.0.1", "/", "session").unwrap());
+        assert!(jar.list(None).is_empty());
+    }
+
+    #[test]
+    fn test_cookie_jar_persists_to_disk() {
+        let path = std::env::temp_dir().join(format!("cosmo-cookies-{}.json", Uuid::new_v4()));
+        let jar = cookies::CookieJar::load(path.clone());
+        jar.upsert(&cookies::CosmoCookie {
+            name: "token".to_string(),
+            value: "xyz".to_string(),
+            domain: "api.example.com".to_string(),
+            path: "/".to_string(),
+            host_only: false,
+            secure: true,
+            http_only: false,
+            expires: Some("2099-01-01T00:00:00+00:00".to_string()),
+        }).unwrap();
+
+        let reloaded = cookies::CookieJar::load(path.clone());
+        let listed = reloaded.list(Some(".api.example.com"));
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].value, "xyz");
+        assert_eq!(listed[0].expires.as_deref(), Some("2099-01-01T00:00:00+00:00"));
+
+        reloaded.clear(Some("api.example.com")).unwrap();
+        assert!(cookies::CookieJar::load(path.clone()).list(None).is_empty());
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn test_response_cookies_saved_in_background() {
+        let mut server = Server::new_async().await;
+        let _login = server.mock("POST", "/login")
+            .with_header("set-cookie", "session=abc123; Path=/")
+            .create_async().await;
+
+        let path = std::env::temp_dir().join(format!("cosmo-cookies-{}.json", Uuid::new_v4()));
+        let jar = Arc::new(cookies::CookieJar::load(path.clone()));
+        let clients = RequestClients::new(None, ClientSettings::default(), Some(jar));
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/login", server.url()),
+            ..Default::default()
+        };
+        send_cosmo_request(None, clients, request, CosmoTimeouts::default()).await.unwrap();
+
+        let mut saved = Vec::new();
+        for _ in 0..100 {
+            saved = cookies::CookieJar::load(path.clone()).list(None);
+            if !saved.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(saved.len(), 1);
+        assert_eq!(saved[0].value, "abc123");
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[test]
+    fn test_parse_method() {
+        assert_eq!(parse_method("delete").unwrap(), reqwest::Method::DELETE);
+        assert_eq!(parse_method(" trace ").unwrap(), reqwest::Method::TRACE);
+        assert_eq!(parse_method("MKCOL").unwrap().as_str(), "MKCOL");
+        let error = parse_method("GE T").unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        assert_eq!(error.message, "Invalid HTTP method: 'GE T'");
+        assert_eq!(parse_method("").unwrap_err().error_type, CosmoErrorType::ValidationError);
+    }
+
+    /// Accepts connections on a local port but never answers them.
+    async fn silent_server() -> (String, tokio::task::JoinHandle<()>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let mut sockets = Vec::new();
+            while let Ok((socket, _)) = listener.accept().await {
+                sockets.push(socket);
+            }
+        });
+        (format!("http://{}", addr), handle)
+    }
+
+    /// Returns a local address with nothing listening on it.
+    fn refused_addr() -> std::net::SocketAddr {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.local_addr().unwrap()
+    }
+
+    /// Answers each connection's first request with whatever `reply` returns
+    /// for the raw request text, then closes it. `None` leaves the request
+    /// hanging; an empty reply hangs up without answering.
+    async fn reply_server<F>(reply: F) -> String
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let reply = Arc::new(reply);
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let reply = reply.clone();
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        return;
+                    }
+                    match reply(&String::from_utf8_lossy(&buf[..n])) {
+                        Some(text) => {
+                            let _ = socket.write_all(text.as_bytes()).await;
+                        }
+                        None => tokio::time::sleep(Duration::from_secs(60)).await,
+                    }
+                });
+            }
+        });
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_read_timeout() {
+        let (url, _server) = silent_server().await;
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            timeouts: Some(CosmoTimeouts { read_ms: Some(100), ..Default::default() }),
+            ..Default::default()
+        };
+
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert!(matches!(error.error_type, CosmoErrorType::TimeoutError));
+        assert!(error.message.contains("read"), "unexpected message: {}", error.message);
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout() {
+        let (url, _server) = silent_server().await;
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            timeouts: Some(CosmoTimeouts { total_ms: Some(100), ..Default::default() }),
+            ..Default::default()
+        };
+
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert!(matches!(error.error_type, CosmoErrorType::TimeoutError));
+        assert!(error.message.contains("total"), "unexpected message: {}", error.message);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_in_flight_request() {
+        let (url, _server) = silent_server().await;
+        let in_flight = std::sync::Arc::new(InFlightRequests::default());
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            request_id: Some("req-1".to_string()),
+            ..Default::default()
+        };
+
+        let runner = in_flight.clone();
+        let task = tokio::spawn(async move {
+            runner.run("req-1", do_execute_cosmo_request(None, request)).await
+        });
+        tokio::time::sleep(Duration::from_millis(100)).await;
+
+        let duplicate = in_flight.run("req-1", async { Ok(()) }).await.unwrap_err();
+        assert_eq!(duplicate.error_type, CosmoErrorType::ValidationError);
+        assert!(in_flight.cancel("req-1"));
+        let error = task.await.unwrap().unwrap_err();
+        assert!(matches!(error.error_type, CosmoErrorType::Cancelled));
+        assert!(!in_flight.cancel("req-1"));
+    }
+
+    #[tokio::test]
+    async fn test_request_through_proxy() {
+        let mut proxy_server = Server::new_async().await;
+        let _m = proxy_server.mock("GET", mockito::Matcher::Any)
+            .match_header("host", "api.example.test")
+            .match_header("proxy-authorization", "Basic dXNlcjpzZWNyZXQ=")
+            .with_status(200)
+            .with_body("proxied")
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: "http://api.example.test/hello".to_string(),
+            proxy: Some(CosmoProxy {
+                url: proxy_server.url(),
+                username: Some("user".to_string()),
+                password: Some("secret".to_string()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "proxied");
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_proxy() {
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: "http://api.example.test/hello".to_string(),
+            proxy: Some(CosmoProxy {
+                url: format!("socks5h://user:secret@{}", refused_addr()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert!(matches!(error.error_type, CosmoErrorType::ProxyError));
+        assert!(!error.message.contains("secret"));
+
+        let invalid = CosmoRequest {
+            method: "GET".to_string(),
+            url: "http://api.example.test/hello".to_string(),
+            proxy: Some(CosmoProxy { url: "not a proxy".to_string(), ..Default::default() }),
+            ..Default::default()
+        };
+        let error = do_execute_cosmo_request(None, invalid).await.unwrap_err();
+        assert!(matches!(error.error_type, CosmoErrorType::ProxyError));
+    }
+
+    #[test]
+    fn test_proxy_bypass_list() {
+        let proxy = CosmoProxy {
+            url: "http://proxy.local:3128".to_string(),
+            no_proxy: vec!["localhost".to_string(), ".internal".to_string(), "10.0.0.0/8".to_string()],
+            ..Default::default()
+        };
+        let applies = |url: &str| proxy.applies_to(&reqwest::Url::parse(url).unwrap());
+
+        assert!(applies("https://example.com/"));
+        assert!(!applies("http://localhost:8080/"));
+        assert!(!applies("https://api.internal/"));
+        assert!(!applies("https://internal/"));
+        assert!(applies("https://notinternal/"));
+        assert!(!applies("http://10.1.2.3/"));
+        assert!(applies("http://11.1.2.3/"));
+        assert!(!CosmoProxy { enabled: false, ..proxy.clone() }.applies_to(&reqwest::Url::parse("https://example.com/").unwrap()));
+    }
+
+    /// Files and server config for an mTLS test: a CA, a server certificate for
+    /// `localhost` and a client certificate (PEM and PKCS#12), both signed by it.
+    struct TestPki {
+        dir: std::path::PathBuf,
+        server_config: Arc<rustls::ServerConfig>,
+        /// Serves `127.0.0.1` from the same CA, with client certificates optional
+        ip_server_config: Arc<rustls::ServerConfig>,
+    }
+
+    fn test_pki() -> TestPki {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+
+        let server_key = KeyPair::generate().unwrap();
+        let server = CertificateParams::new(vec!["localhost".to_string()]).unwrap()
+            .signed_by(&server_key, &ca, &ca_key).unwrap();
+        let client_key = KeyPair::generate().unwrap();
+        let client = CertificateParams::new(vec!["client".to_string()]).unwrap()
+            .signed_by(&client_key, &ca, &ca_key).unwrap();
+
+        let dir = std::env::temp_dir().join(format!("cosmo-tls-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("ca.pem"), ca.pem()).unwrap();
+        std::fs::write(dir.join("client.pem"), client.pem()).unwrap();
+        std::fs::write(dir.join("client.key"), client_key.serialize_pem()).unwrap();
+
+        let mut p12 = p12_keystore::KeyStore::new();
+        p12.add_entry("client", p12_keystore::KeyStoreEntry::PrivateKeyChain(p12_keystore::PrivateKeyChain::new(
+            client_key.serialize_der(),
+            [1u8; 20],
+            vec![p12_keystore::Certificate::from_der(client.der()).unwrap()],
+        )));
+        std::fs::write(dir.join("client.p12"), p12.writer("hunter2").write().unwrap()).unwrap();
+
+        let mut client_roots = rustls::RootCertStore::empty();
+        client_roots.add(ca.der().clone()).unwrap();
+        let provider = Arc::new(rustls::crypto::ring::default_provider());
+        let client_roots = Arc::new(client_roots);
+        let client_verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(client_roots.clone(), provider.clone())
+            .build().unwrap();
+        let server_config = rustls::ServerConfig::builder_with_provider(provider.clone())
+            .with_safe_default_protocol_versions().unwrap()
+            .with_client_cert_verifier(client_verifier)
+            .with_single_cert(
+                vec![server.der().clone()],
+                rustls::pki_types::PrivateKeyDer::Pkcs8(server_key.serialize_der().into()),
+            ).unwrap();
+
+        let ip_key = KeyPair::generate().unwrap();
+        let ip_server = CertificateParams::new(vec!["127.0.0.1".to_string()]).unwrap()
+            .signed_by(&ip_key, &ca, &ca_key).unwrap();
+        let optional_verifier = rustls::server::WebPkiClientVerifier::builder_with_provider(client_roots, provider.clone())
+            .allow_unauthenticated()
+            .build().unwrap();
+        let ip_server_config = rustls::ServerConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions().unwrap()
+            .with_client_cert_verifier(optional_verifier)
+            .with_single_cert(
+                vec![ip_server.der().clone()],
+                rustls::pki_types::PrivateKeyDer::Pkcs8(ip_key.serialize_der().into()),
+            ).unwrap();
+
+        TestPki { dir, server_config: Arc::new(server_config), ip_server_config: Arc::new(ip_server_config) }
+    }
+
+    /// Serves `ok` over TLS, requiring a client certificate.
+    async fn mtls_server(config: Arc<rustls::ServerConfig>) -> String {
+        let port = tls_server(config, Arc::new(|_| {
+            "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok".to_string()
+        })).await;
+        format!("https://localhost:{}/", port)
+    }
+
+    /// Serves the response `reply` builds from whether the client presented a
+    /// certificate, and returns the port.
+    async fn tls_server(config: Arc<rustls::ServerConfig>, reply: Arc<dyn Fn(bool) -> String + Send + Sync>) -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let acceptor = tokio_rustls::TlsAcceptor::from(config);
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                let (acceptor, reply) = (acceptor.clone(), reply.clone());
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(socket).await else { return };
+                    let mut buf = [0u8; 4096];
+                    if stream.read(&mut buf).await.unwrap_or(0) > 0 {
+                        let presented = stream.get_ref().1.peer_certificates().is_some();
+                        let _ = stream.write_all(reply(presented).as_bytes()).await;
+                        let _ = stream.shutdown().await;
+                    }
+                });
+            }
+        });
+        port
+    }
+
+    #[tokio::test]
+    async fn test_custom_ca_and_client_certificates() {
+        let pki = test_pki();
+        let url = mtls_server(pki.server_config.clone()).await;
+        let path = |name: &str| pki.dir.join(name).to_string_lossy().into_owned();
+        let send = |tls: CosmoTlsSettings| do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: url.clone(),
+            tls,
+            ..Default::default()
+        });
+
+        // Server certificate is signed by an unknown CA
+        assert!(send(CosmoTlsSettings::default()).await.is_err());
+
+        // Trusted CA, but the server requires a client certificate
+        let trusted = CosmoTlsSettings { ca_certificates: vec![path("ca.pem")], ..Default::default() };
+        assert!(send(trusted.clone()).await.is_err());
+
+        let pem_identity = tls::CosmoClientCertificate {
+            host: "localhost".to_string(),
+            identity: tls::ClientIdentity::Pem { cert_path: path("client.pem"), key_path: path("client.key") },
+        };
+        let response = send(CosmoTlsSettings {
+            client_certificates: vec![pem_identity.clone()],
+            ..trusted.clone()
+        }).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "ok");
+
+        // Certificates for other hosts are not presented
+        let other_host = tls::CosmoClientCertificate { host: "*.example.com".to_string(), ..pem_identity };
+        assert!(send(CosmoTlsSettings { client_certificates: vec![other_host], ..trusted }).await.is_err());
+
+        // Verification switched off, identity from a PKCS#12 bundle
+        let response = send(CosmoTlsSettings {
+            skip_verification: true,
+            client_certificates: vec![tls::CosmoClientCertificate {
+                host: "localhost".to_string(),
+                identity: tls::ClientIdentity::Pkcs12 { path: path("client.p12"), password: "hunter2".to_string() },
+            }],
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.status, 200);
+
+        let _ = std::fs::remove_dir_all(&pki.dir);
+    }
+
+    #[tokio::test]
+    async fn test_client_certificate_follows_redirect_host() {
+        let pki = test_pki();
+        let path = |name: &str| pki.dir.join(name).to_string_lossy().into_owned();
+        let second = tls_server(pki.ip_server_config.clone(), Arc::new(|presented| {
+            let body = if presented { "cert" } else { "none" };
+            format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body)
+        })).await;
+        let first = tls_server(pki.server_config.clone(), Arc::new(move |_| {
+            format!(
+                "HTTP/1.1 302 Found\r\nlocation: https://127.0.0.1:{}/\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                second,
+            )
+        })).await;
+
+        // The certificate is only for localhost, so the second host gets none
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("https://localhost:{}/", first),
+            tls: CosmoTlsSettings {
+                ca_certificates: vec![path("ca.pem")],
+                client_certificates: vec![tls::CosmoClientCertificate {
+                    host: "localhost".to_string(),
+                    identity: tls::ClientIdentity::Pem { cert_path: path("client.pem"), key_path: path("client.key") },
+                }],
+                ..Default::default()
+            },
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "none");
+        assert_eq!(response.redirects.len(), 1);
+
+        let _ = std::fs::remove_dir_all(&pki.dir);
+    }
+
+    #[tokio::test]
+    async fn test_client_pool_eviction() {
+        // A remembered handshake tells whether the pool kept a client
+        let url = reqwest::Url::parse("https://example.com/").unwrap();
+        let negotiated = tls::NegotiatedTls { version: "TLSv1.3", cipher_suite: "TLS13_AES_128_GCM_SHA256".to_string() };
+        let settings = |read_ms: u64| ClientSettings { read_timeout_ms: Some(read_ms), ..Default::default() };
+        async fn kept(pool: &ClientPool, settings: &ClientSettings) -> bool {
+            let url = reqwest::Url::parse("https://example.com/").unwrap();
+            pool.client(settings, None).await.unwrap().negotiated_for(&url).is_some()
+        }
+
+        // The least recently used client makes room
+        let pool = ClientPool::with_limits(2, Duration::from_secs(60));
+        pool.client(&settings(1), None).await.unwrap().remember_negotiated(&url, negotiated.clone());
+        pool.client(&settings(2), None).await.unwrap().remember_negotiated(&url, negotiated.clone());
+        assert!(kept(&pool, &settings(1)).await);
+        pool.client(&settings(3), None).await.unwrap();
+        assert!(kept(&pool, &settings(1)).await);
+        assert!(!kept(&pool, &settings(2)).await);
+
+        // Idle clients are dropped
+        let pool = ClientPool::with_limits(8, Duration::from_millis(50));
+        pool.client(&settings(1), None).await.unwrap().remember_negotiated(&url, negotiated.clone());
+        tokio::time::sleep(Duration::from_millis(80)).await;
+        assert!(!kept(&pool, &settings(1)).await);
+
+        // Clients are rebuilt once a certificate file they read changes
+        let pki = test_pki();
+        let ca = pki.dir.join("ca.pem");
+        let settings = ClientSettings {
+            tls: CosmoTlsSettings { ca_certificates: vec![ca.to_string_lossy().into_owned()], ..Default::default() },
+            ..Default::default()
+        };
+        let pool = ClientPool::default();
+        pool.client(&settings, None).await.unwrap().remember_negotiated(&url, negotiated);
+        assert!(kept(&pool, &settings).await);
+        let modified = std::time::SystemTime::now() + Duration::from_secs(60);
+        std::fs::File::options().write(true).open(&ca).unwrap().set_modified(modified).unwrap();
+        assert!(!kept(&pool, &settings).await);
+        let _ = std::fs::remove_dir_all(&pki.dir);
+    }
+
+    #[test]
+    fn test_tls_settings_parse() {
+        let prefs = preferences::parse_workspace_preferences(r#"{"tls": {
+            "ca_certificates": ["/etc/ca.pem"],
+            "client_certificates": [
+                {"host": "*.staging.internal", "format": "pkcs12", "path": "/certs/staging.p12"},
+                {"host": "api.internal", "format": "pem", "cert_path": "/certs/api.pem", "key_path": "/certs/api.key"}
+            ]
+        }}"#);
+
+        assert!(!prefs.tls.skip_verification);
+        let staging = prefs.tls.for_host("web.staging.internal");
+        assert_eq!(staging.client_certificates.len(), 1);
+        assert!(matches!(staging.client_certificates[0].identity, tls::ClientIdentity::Pkcs12 { .. }));
+        assert!(prefs.tls.for_host("staging.internal").client_certificates.is_empty());
+        assert_eq!(prefs.tls.for_host("API.internal").client_certificates[0].host, "api.internal");
+    }
+
+    #[tokio::test]
+    async fn test_redirect_chain() {
+        let mut server = Server::new_async().await;
+        let _start = server.mock("POST", "/start")
+            .with_status(302)
+            .with_header("location", "/middle")
+            .create_async().await;
+        let _middle = server.mock("GET", "/middle")
+            .match_header("authorization", "Bearer token")
+            .match_header("content-type", mockito::Matcher::Missing)
+            .with_status(303)
+            .with_header("location", format!("{}/end", server.url()).as_str())
+            .create_async().await;
+        let _end = server.mock("GET", "/end")
+            .with_status(200)
+            .with_body("done")
+            .create_async().await;
+
+        let request = |redirect| CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/start", server.url()),
+            headers: Some(HashMap::from([
+                ("Authorization".to_string(), "Bearer token".to_string()),
+                ("Content-Type".to_string(), "application/json".to_string()),
+            ])),
+            body: Some("{}".to_string().into()),
+            redirect,
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request(None)).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "done");
+        assert_eq!(response.url, format!("{}/end", server.url()));
+        let hops: Vec<(&str, u16)> = response.redirects.iter().map(|r| (r.url.as_str(), r.status)).collect();
+        assert_eq!(hops, vec![
+            (format!("{}/start", server.url()).as_str(), 302),
+            (format!("{}/middle", server.url()).as_str(), 303),
+        ]);
+        assert_eq!(find_header(&response.redirects[0].headers, "location"), Some("/middle"));
+
+        let manual = CosmoRedirectPolicy { follow: false, ..Default::default() };
+        let response = do_execute_cosmo_request(None, request(Some(manual))).await.unwrap();
+        assert_eq!(response.status, 302);
+        assert!(response.redirects.is_empty());
+
+        let limited = CosmoRedirectPolicy { max_redirects: 1, ..Default::default() };
+        let error = do_execute_cosmo_request(None, request(Some(limited))).await.unwrap_err();
+        assert!(error.message.contains("Too many redirects"));
+        assert_eq!(error.error_type, CosmoErrorType::TooManyRedirects);
+    }
+
+    #[tokio::test]
+    async fn test_redirect_strips_auth_across_origins() {
+        let mut origin = Server::new_async().await;
+        let mut other = Server::new_async().await;
+        let _redirect = origin.mock("PUT", "/resource")
+            .with_status(307)
+            .with_header("location", format!("{}/moved", other.url()).as_str())
+            .expect(2)
+            .create_async().await;
+        let _stripped = other.mock("PUT", "/moved")
+            .match_header("authorization", mockito::Matcher::Missing)
+            .match_body("payload")
+            .with_body("stripped")
+            .create_async().await;
+        let _kept = other.mock("PUT", "/moved")
+            .match_header("authorization", "Bearer token")
+            .match_body("payload")
+            .with_body("kept")
+            .create_async().await;
+
+        let request = |keep_auth_across_origins| CosmoRequest {
+            method: "PUT".to_string(),
+            url: format!("{}/resource", origin.url()),
+            headers: Some(HashMap::from([("Authorization".to_string(), "Bearer token".to_string())])),
+            body: Some("payload".to_string().into()),
+            redirect: Some(CosmoRedirectPolicy { keep_auth_across_origins, ..Default::default() }),
+            ..Default::default()
+        };
+
+        let response = do_execute_cosmo_request(None, request(false)).await.unwrap();
+        assert_eq!(response.body, "stripped");
+        let response = do_execute_cosmo_request(None, request(true)).await.unwrap();
+        assert_eq!(response.body, "kept");
+    }
+
+    #[tokio::test]
+    async fn test_structured_bodies() {
+        let dir = std::env::temp_dir().join(format!("cosmo-body-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("avatar.png");
+        std::fs::write(&file, [0x89, b'P', b'N', b'G', 0x00, 0xff]).unwrap();
+        let file_path = file.to_string_lossy().into_owned();
+
+        let mut server = Server::new_async().await;
+        let _form = server.mock("POST", "/form")
+            .match_header("content-type", "application/x-www-form-urlencoded")
+            .match_body("q=a+b%26c&lang=en")
+            .with_body("form")
+            .create_async().await;
+        let _multipart = server.mock("POST", "/upload")
+            .match_header("content-type", mockito::Matcher::Regex("^multipart/form-data; boundary=".to_string()))
+            .match_body(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::Regex("name=\"title\"\r\nContent-Type: text/plain\r\n\r\nHello\r\n".to_string()),
+                mockito::Matcher::Regex("name=\"avatar\"; filename=\"avatar.png\"\r\nContent-Type: image/png\r\n".to_string()),
+            ]))
+            .with_body("multipart")
+            .create_async().await;
+        let _quoted = server.mock("POST", "/upload-quoted")
+            .match_body(mockito::Matcher::Regex("name=\"avatar\"; filename=\"evil%22.png%0D%0AX-Injected: 1\"\r\n".to_string()))
+            .with_body("quoted")
+            .create_async().await;
+        let _binary = server.mock("PUT", "/blob")
+            .match_header("content-type", "application/vnd.custom")
+            .match_body(vec![0x89, b'P', b'N', b'G', 0x00, 0xff])
+            .with_body("binary")
+            .expect(3)
+            .create_async().await;
+        let _moved = server.mock("PUT", "/old-blob")
+            .with_status(307)
+            .with_header("location", "/blob")
+            .create_async().await;
+
+        let send = |path: &str, method: &str, body: serde_json::Value, headers: Option<HashMap<String, String>>| {
+            let request = CosmoRequest {
+                method: method.to_string(),
+                url: format!("{}{}", server.url(), path),
+                headers,
+                body: Some(serde_json::from_value(body).unwrap()),
+                ..Default::default()
+            };
+            do_execute_cosmo_request(None, request)
+        };
+
+        let response = send("/form", "POST", serde_json::json!({
+            "type": "urlencoded",
+            "fields": [{"name": "q", "value": "a b&c"}, {"name": "lang", "value": "en"}]
+        }), None).await.unwrap();
+        assert_eq!(response.body, "form");
+
+        // A user-supplied Content-Type can't break the multipart boundary
+        let response = send("/upload", "POST", serde_json::json!({
+            "type": "multipart",
+            "parts": [
+                {"type": "text", "name": "title", "value": "Hello", "content_type": "text/plain"},
+                {"type": "file", "name": "avatar", "path": file_path}
+            ]
+        }), Some(HashMap::from([("content-type".to_string(), "multipart/form-data".to_string())]))).await.unwrap();
+        assert_eq!(response.body, "multipart");
+
+        // Filenames can't end the quoted parameter or add header lines
+        let response = send("/upload-quoted", "POST", serde_json::json!({
+            "type": "multipart",
+            "parts": [{"type": "file", "name": "avatar", "path": file_path, "filename": "evil\".png\r\nX-Injected: 1"}]
+        }), None).await.unwrap();
+        assert_eq!(response.body, "quoted");
+
+        let response = send("/blob", "PUT", serde_json::json!({
+            "type": "file", "path": file_path, "content_type": "application/vnd.custom"
+        }), None).await.unwrap();
+        assert_eq!(response.body, "binary");
+
+        // Streamed files are sent again after a 307
+        let response = send("/old-blob", "PUT", serde_json::json!({
+            "type": "file", "path": file_path, "content_type": "application/vnd.custom"
+        }), None).await.unwrap();
+        assert_eq!((response.body.as_str(), response.redirects.len()), ("binary", 1));
+
+        let response = send("/blob", "PUT", serde_json::json!({
+            "type": "base64", "data": "iVBORwD/"
+        }), Some(HashMap::from([("Content-Type".to_string(), "application/vnd.custom".to_string())]))).await.unwrap();
+        assert_eq!(response.body, "binary");
+
+        let missing = send("/blob", "PUT", serde_json::json!({"type": "file", "path": dir.join("missing.bin")}), None).await;
+        assert!(missing.unwrap_err().message.contains("missing.bin"));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_plain_string_body_is_raw() {
+        let request: CosmoRequest = serde_json::from_value(serde_json::json!({
+            "method": "POST", "url": "http://localhost/", "body": "hello"
+        })).unwrap();
+        assert_eq!(request.body, Some(CosmoBody::Raw { content: "hello".to_string(), content_type: None }));
+
+        let request: CosmoRequest = serde_json::from_value(serde_json::json!({
+            "method": "GET", "url": "http://localhost/", "body": null
+        })).unwrap();
+        assert_eq!(request.body, None);
+    }
+
+    #[test]
+    fn test_sse_parser_handles_split_chunks() {
+        let stream = "\u{feff}: comment\r\nid: 1\r\nevent: greeting\r\ndata: h\u{e9}llo\r\ndata: w\u{f6}rld\r\n\r\ndata:plain\n\nretry: 3000\nid: bad\0id\ndata: \n\nevent: ignored\n\n".as_bytes();
+
+        // Feed one byte at a time so line breaks and characters are split
+        let mut parser = sse::SseParser::default();
+        let events: Vec<sse::SseEvent> = stream.iter().flat_map(|b| parser.feed(std::slice::from_ref(b))).collect();
+
+        assert_eq!(events, vec![
+            sse::SseEvent { id: Some("1".to_string()), event: "greeting".to_string(), data: "h\u{e9}llo\nw\u{f6}rld".to_string(), retry: None },
+            sse::SseEvent { id: Some("1".to_string()), event: "message".to_string(), data: "plain".to_string(), retry: None },
+            sse::SseEvent { id: Some("1".to_string()), event: "message".to_string(), data: String::new(), retry: Some(3000) },
+        ]);
+
+        // An unterminated event at the end of the stream is never dispatched
+        let mut parser = sse::SseParser::default();
+        assert!(parser.feed(b"data: partial\n").is_empty());
+    }
+
+    #[test]
+    fn test_stream_messages_and_close() {
+        let message = stream::StreamMessage::Event(sse::SseEvent {
+            id: None,
+            event: "message".to_string(),
+            data: "hi".to_string(),
+            retry: None,
+        });
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            serde_json::json!({"type": "event", "id": null, "event": "message", "data": "hi", "retry": null}),
+        );
+        assert_eq!(
+            serde_json::to_value(stream::StreamMessage::End { closed: true }).unwrap(),
+            serde_json::json!({"type": "end", "closed": true}),
+        );
+
+        let in_flight = InFlightRequests::default();
+        let (handle, registration) = futures_util::future::AbortHandle::new_pair();
+        in_flight.track_stream("req-1", "channel-1", handle);
+
+        assert!(!in_flight.close_stream("channel-2"));
+        assert!(in_flight.close_stream("channel-1"));
+        assert!(registration.handle().is_aborted());
+    }
+
+    #[test]
+    fn test_stream_framing_rules() {
+        let rules = vec![
+            CosmoStreamRule { content_type: "text/plain".to_string(), framing: StreamFraming::Chunks },
+            CosmoStreamRule { content_type: "application/*".to_string(), framing: StreamFraming::Ndjson },
+        ];
+
+        assert_eq!(stream::framing_for(Some("text/event-stream; charset=utf-8"), &[]), Some(StreamFraming::Sse));
+        assert_eq!(stream::framing_for(Some("application/x-ndjson"), &[]), Some(StreamFraming::Ndjson));
+        assert_eq!(stream::framing_for(Some("application/json"), &[]), None);
+        assert_eq!(stream::framing_for(None, &rules), None);
+        assert_eq!(stream::framing_for(Some("Text/Plain"), &rules), Some(StreamFraming::Chunks));
+        assert_eq!(stream::framing_for(Some("application/json"), &rules), Some(StreamFraming::Ndjson));
+    }
+
+    #[test]
+    fn test_ndjson_and_chunk_framing() {
+        let mut lines = stream::LineSplitter::default();
+        let mut out = lines.feed(b"{\"n\":1}\r\n\n{\"n\"");
+        out.extend(lines.feed(b":2}\nnot json"));
+        out.extend(lines.finish());
+        assert_eq!(out, vec![r#"{"n":1}"#, r#"{"n":2}"#, "not json"]);
+
+        // "é" split across two chunks is emitted whole with the second one
+        let mut chunks = stream::ChunkFramer::new(Some("text/plain; charset=utf-8"));
+        let first = chunks.feed(&[b'a', 0xc3], false);
+        let second = chunks.feed(&[0xa9, b'b'], false);
+        assert_eq!(first, stream::StreamMessage::Chunk { offset: 0, data_base64: "YcM=".to_string(), text: Some("a".to_string()) });
+        assert_eq!(second, stream::StreamMessage::Chunk { offset: 2, data_base64: "qWI=".to_string(), text: Some("\u{e9}b".to_string()) });
+
+        let mut binary = stream::ChunkFramer::new(Some("application/octet-stream"));
+        assert!(matches!(binary.feed(&[0xff], false), stream::StreamMessage::Chunk { text: None, .. }));
+    }
+
+    #[tokio::test]
+    async fn test_stream_opt_in_returns_before_body() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/logs")
+            .with_header("content-type", "text/plain")
+            .with_chunked_body(|w| {
+                w.write_all(b"line 1\n")?;
+                std::thread::sleep(Duration::from_secs(1));
+                w.write_all(b"line 2\n")
+            })
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/logs", server.url()),
+            stream: Some(StreamFraming::Chunks),
+            ..Default::default()
+        };
+        let response = tokio::time::timeout(Duration::from_millis(500), do_execute_cosmo_request(None, request))
+            .await
+            .expect("streamed response should return once headers arrive")
+            .unwrap();
+        assert!(response.is_stream);
+        assert_eq!(response.stream_framing, Some(StreamFraming::Chunks));
+    }
+
+    /// Accepts one WebSocket connection, checks the handshake and echoes every
+    /// text and binary frame back.
+    async fn websocket_echo_server() -> String {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            // The callback signature is fixed by tungstenite
+            #[allow(clippy::result_large_err)]
+            let check = |request: &Request, mut response: Response| {
+                assert_eq!(request.headers().get("x-api-key").unwrap(), "secret");
+                assert_eq!(request.headers().get("sec-websocket-protocol").unwrap(), "chat.v2, chat.v1");
+                response.headers_mut().insert("sec-websocket-protocol", "chat.v2".parse().unwrap());
+                Ok(response)
+            };
+            let mut ws = tokio_tungstenite::accept_hdr_async(socket, check).await.unwrap();
+            while let Some(Ok(message)) = ws.next().await {
+                if message.is_text() || message.is_binary() {
+                    ws.send(message).await.unwrap();
+                }
+            }
+        });
+        format!("ws://{}/socket", addr)
+    }
+
+    #[tokio::test]
+    async fn test_websocket_session() {
+        use websocket::{CosmoWebSocketFrame, CosmoWebSocketRequest, WebSocketEvent};
+
+        let url = websocket_echo_server().await;
+        let sessions = WebSocketSessions::default();
+        let (events_tx, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let opened = sessions
+            .open(
+                CosmoWebSocketRequest {
+                    url,
+                    headers: Some(HashMap::from([("X-Api-Key".to_string(), "secret".to_string())])),
+                    subprotocols: vec!["chat.v2".to_string(), "chat.v1".to_string()],
+                    connection_id: Some("ws-1".to_string()),
+                },
+                &CosmoTlsSettings::default(),
+                CosmoTimeouts::default(),
+                move |event| events_tx.send(event).unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(opened.connection_id, "ws-1");
+        assert_eq!(opened.status, 101);
+        assert_eq!(opened.protocol.as_deref(), Some("chat.v2"));
+
+        async fn next<T>(events: &mut tokio::sync::mpsc::UnboundedReceiver<T>) -> T {
+            tokio::time::timeout(Duration::from_secs(5), events.recv()).await.unwrap().unwrap()
+        }
+        sessions.send("ws-1", CosmoWebSocketFrame::Text { data: "hello".to_string() }).unwrap();
+        assert_eq!(next(&mut events).await, WebSocketEvent::Text { data: "hello".to_string() });
+        sessions.send("ws-1", CosmoWebSocketFrame::Binary { data_base64: "AP8=".to_string() }).unwrap();
+        assert_eq!(next(&mut events).await, WebSocketEvent::Binary { data_base64: "AP8=".to_string() });
+        sessions.send("ws-1", CosmoWebSocketFrame::Ping { data_base64: "cGluZw==".to_string() }).unwrap();
+        assert_eq!(next(&mut events).await, WebSocketEvent::Pong { data_base64: "cGluZw==".to_string() });
+
+        assert!(sessions.close("ws-1", Some(1000), Some("bye".to_string())));
+        assert_eq!(next(&mut events).await, WebSocketEvent::Close { code: Some(1000), reason: "bye".to_string(), by_client: true });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(sessions.send("ws-1", CosmoWebSocketFrame::Text { data: "late".to_string() }).is_err());
+        assert!(!sessions.close("ws-1", None, None));
+    }
+
+    #[tokio::test]
+    async fn test_websocket_open_reserves_id() {
+        use websocket::CosmoWebSocketRequest;
+
+        // Accepts the TCP connection but never answers the handshake
+        let (silent, _server) = silent_server().await;
+        let request = |url: String| CosmoWebSocketRequest {
+            url,
+            headers: None,
+            subprotocols: Vec::new(),
+            connection_id: Some("ws-1".to_string()),
+        };
+        let sessions = WebSocketSessions::default();
+        let tls = CosmoTlsSettings::default();
+        let silent = silent.replacen("http", "ws", 1);
+        let stalled = sessions.open(request(silent.clone()), &tls, CosmoTimeouts::default(), |_| {});
+        let duplicate = async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            sessions.open(request("ws://127.0.0.1:1/".to_string()), &tls, CosmoTimeouts::default(), |_| {}).await
+        };
+        tokio::select! {
+            _ = stalled => panic!("handshake should not complete"),
+            error = duplicate => {
+                let error = error.unwrap_err();
+                assert!(error.message.contains("already open"));
+                assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+            }
+        }
+
+        // A failed open releases the id again
+        let refused = format!("ws://{}/", refused_addr());
+        let error = sessions.open(request(refused.clone()), &tls, CosmoTimeouts::default(), |_| {}).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused, "{:?}", error);
+        let error = sessions.open(request(refused), &tls, CosmoTimeouts::default(), |_| {}).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused, "{:?}", error);
+
+        // A handshake that never completes times out, releasing the id too
+        let quick = CosmoTimeouts { connect_ms: Some(100), ..Default::default() };
+        for _ in 0..2 {
+            let error = sessions.open(request(silent.clone()), &tls, quick, |_| {}).await.unwrap_err();
+            assert_eq!(error.error_type, CosmoErrorType::TimeoutError, "{:?}", error);
+            assert!(error.message.contains("connect phase (limit: 100 ms)"), "{}", error.message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_graphql_operation() {
+        let mut server = Server::new_async().await;
+        let _mock = server.mock("POST", "/graphql")
+            .match_header("content-type", "application/json")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "query": "query User($id: ID!) { user(id: $id) { name email } }",
+                "variables": {"id": "42"},
+                "operationName": "User"
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(r#"{
+                "data": {"user": {"name": "Ada", "email": null}},
+                "errors": [{"message": "Not authorized to read email", "locations": [{"line": 1, "column": 43}], "path": ["user", "email"]}]
+            }"#)
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/graphql", server.url()),
+            body: Some(serde_json::from_value(serde_json::json!({
+                "type": "graphql",
+                "query": "query User($id: ID!) { user(id: $id) { name email } }",
+                "variables": {"id": "42"},
+                "operation_name": "User"
+            })).unwrap()),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        let result = response.graphql.expect("GraphQL result");
+        assert_eq!(result.data, Some(serde_json::json!({"user": {"name": "Ada", "email": null}})));
+        assert_eq!(result.errors.len(), 1);
+        assert_eq!(result.errors[0].message, "Not authorized to read email");
+        assert_eq!(result.errors[0].locations, vec![graphql::GraphQLLocation { line: 1, column: 43 }]);
+        assert_eq!(result.errors[0].path, vec![serde_json::json!("user"), serde_json::json!("email")]);
+
+        // Non-GraphQL bodies and responses leave the field unset
+        assert_eq!(graphql::parse_result("<html>Bad gateway</html>"), None);
+        assert_eq!(graphql::parse_result(r#"{"errors": [{"message": "boom"}], "data": null}"#).unwrap().data, None);
+    }
+
+    #[tokio::test]
+    async fn test_graphql_introspection_summary() {
+        let type_ref = |kind: &str, name: Option<&str>, of_type: serde_json::Value| {
+            serde_json::json!({"kind": kind, "name": name, "ofType": of_type})
+        };
+        let non_null = |inner: serde_json::Value| type_ref("NON_NULL", None, inner);
+        let named = |kind: &str, name: &str| type_ref(kind, Some(name), serde_json::Value::Null);
+        let schema = serde_json::json!({"data": {"__schema": {
+            "queryType": {"name": "Query"},
+            "mutationType": null,
+            "subscriptionType": null,
+            "types": [
+                {"kind": "OBJECT", "name": "Query", "description": null, "fields": [
+                    {"name": "users", "description": "All users", "isDeprecated": false,
+                     "args": [{"name": "first", "description": null, "type": named("SCALAR", "Int"), "defaultValue": "10"}],
+                     "type": non_null(type_ref("LIST", None, non_null(named("OBJECT", "User"))))}
+                ], "inputFields": null, "enumValues": null, "possibleTypes": null},
+                {"kind": "ENUM", "name": "Role", "description": null, "fields": null, "inputFields": null,
+                 "enumValues": [{"name": "ADMIN"}, {"name": "MEMBER"}], "possibleTypes": null},
+                {"kind": "OBJECT", "name": "__Schema", "description": null, "fields": [], "inputFields": null, "enumValues": null, "possibleTypes": null}
+            ]
+        }}});
+
+        let mut server = Server::new_async().await;
+        let _mock = server.mock("POST", "/graphql")
+            .match_header("authorization", "Bearer token")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({"operationName": "IntrospectionQuery"})))
+            .with_header("content-type", "application/json")
+            .with_body(schema.to_string())
+            .create_async().await;
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/graphql", server.url()),
+            headers: Some(HashMap::from([("Authorization".to_string(), "Bearer token".to_string())])),
+            ..Default::default()
+        };
+        let summary = graphql::introspect(None, request).await.unwrap();
+        assert_eq!(summary.query_type.as_deref(), Some("Query"));
+        assert_eq!(summary.mutation_type, None);
+        assert_eq!(summary.types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>(), vec!["Query", "Role"]);
+
+        let users = &summary.types[0].fields[0];
+        assert_eq!(users.type_name, "[User!]!");
+        assert_eq!(users.description.as_deref(), Some("All users"));
+        assert_eq!(users.args[0].name, "first");
+        assert_eq!(users.args[0].type_name, "Int");
+        assert_eq!(users.args[0].default_value.as_deref(), Some("10"));
+        assert_eq!(summary.types[1].enum_values, vec!["ADMIN", "MEMBER"]);
+    }
+
+    const GREETER_PROTO: &str = r#"syntax = "proto3";
+package test.v1;
+
+import "google/protobuf/timestamp.proto";
+
+service Greeter {
+  rpc SayHello(HelloRequest) returns (HelloReply);
+  rpc Countdown(HelloRequest) returns (stream HelloReply);
+  rpc Collect(stream HelloRequest) returns (HelloReply);
+}
+
+message HelloRequest {
+  string name = 1;
+  int32 count = 2;
+  google.protobuf.Timestamp sent_at = 3;
+}
+
+message HelloReply {
+  string message = 1;
+}
+"#;
+
+    /// `test.v1.Greeter` implemented over dynamic messages.
+    #[derive(Clone)]
+    struct Greeter(prost_reflect::ServiceDescriptor);
+
+    impl tonic::server::NamedService for Greeter {
+        const NAME: &'static str = "test.v1.Greeter";
+    }
+
+    impl tonic::codegen::Service<tonic::codegen::http::Request<tonic::body::Body>> for Greeter {
+        type Response = tonic::codegen::http::Response<tonic::body::Body>;
+        type Error = std::convert::Infallible;
+        type Future = tonic::codegen::BoxFuture<Self::Response, Self::Error>;
+
+        fn poll_ready(&mut self, _cx: &mut std::task::Context<'_>) -> std::task::Poll<Result<(), Self::Error>> {
+            std::task::Poll::Ready(Ok(()))
+        }
+
+        fn call(&mut self, request: tonic::codegen::http::Request<tonic::body::Body>) -> Self::Future {
+            let name = request.uri().path().rsplit('/').next().unwrap_or_default().to_string();
+            let method = self.0.methods().find(|m| m.name() == name).unwrap();
+            Box::pin(async move {
+                let mut server = tonic::server::Grpc::new(grpc::DynamicCodec::new(method.input()));
+                Ok(server.streaming(GreeterMethod(method), request).await)
+            })
+        }
+    }
+
+    struct GreeterMethod(prost_reflect::MethodDescriptor);
+
+    impl tonic::server::StreamingService<prost_reflect::DynamicMessage> for GreeterMethod {
+        type Response = prost_reflect::DynamicMessage;
+        type ResponseStream = tonic::codegen::BoxStream<prost_reflect::DynamicMessage>;
+        type Future = tonic::codegen::BoxFuture<tonic::Response<Self::ResponseStream>, tonic::Status>;
+
+        fn call(&mut self, request: tonic::Request<tonic::Streaming<prost_reflect::DynamicMessage>>) -> Self::Future {
+            let method = self.0.clone();
+            Box::pin(async move {
+                let mut names = Vec::new();
+                let mut count = 0;
+                let mut messages = request.into_inner();
+                while let Some(message) = messages.message().await? {
+                    let json = serde_json::to_value(&message).unwrap();
+                    names.push(json["name"].as_str().unwrap_or_default().to_string());
+                    count = json["count"].as_i64().unwrap_or(0);
+                }
+                if names.iter().any(String::is_empty) {
+                    let mut status = tonic::Status::invalid_argument("name is required");
+                    status.metadata_mut().insert("x-field", "name".parse().unwrap());
+                    return Err(status);
+                }
+
+                let reply = |text: String| {
+                    prost_reflect::DynamicMessage::deserialize(method.output(), serde_json::json!({"message": text}))
+                        .map_err(|e| tonic::Status::internal(e.to_string()))
+                };
+                let replies: Vec<_> = match method.name() {
+                    "SayHello" => vec![reply(format!("Hello, {}", names[0]))],
+                    "Countdown" => (1..=count).rev().map(|i| reply(format!("{} {}", names[0], i))).collect(),
+                    _ => vec![reply(format!("Hello, {}", names.join(" and ")))],
+                };
+                let mut response = tonic::Response::new(Box::pin(futures_util::stream::iter(replies)) as Self::ResponseStream);
+                response.metadata_mut().insert("x-served-by", "greeter".parse().unwrap());
+                Ok(response)
+            })
+        }
+    }
+
+    /// Writes the Greeter proto to a temp dir and returns its path.
+    fn greeter_proto() -> String {
+        let dir = std::env::temp_dir().join(format!("cosmo-grpc-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("greeter.proto");
+        std::fs::write(&path, GREETER_PROTO).unwrap();
+        path.to_string_lossy().into_owned()
+    }
+
+    /// Serves the Greeter along with server reflection, returning the server address.
+    async fn grpc_server(proto_path: &str) -> String {
+        let pool = grpc::load_proto_files(&[proto_path.to_string()], &[]).unwrap();
+        let greeter = Greeter(pool.get_service_by_name("test.v1.Greeter").unwrap());
+        let encoded: &'static [u8] = Box::leak(pool.encode_to_vec().into_boxed_slice());
+        let reflection = tonic_reflection::server::Builder::configure()
+            .register_encoded_file_descriptor_set(encoded)
+            .build_v1()
+            .unwrap();
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(
+            tonic::transport::Server::builder()
+                .add_service(greeter)
+                .add_service(reflection)
+                .serve_with_incoming(tonic::codegen::tokio_stream::wrappers::TcpListenerStream::new(listener)),
+        );
+        format!("http://{}", addr)
+    }
+
+    #[tokio::test]
+    async fn test_grpc_calls_from_proto_files() {
+        let proto_path = greeter_proto();
+        let url = grpc_server(&proto_path).await;
+        let call = |method: &str, messages: Vec<serde_json::Value>| grpc::CosmoGrpcRequest {
+            url: url.clone(),
+            method: method.to_string(),
+            source: grpc::CosmoProtoSource::Files { paths: vec![proto_path.clone()], include_paths: Vec::new() },
+            metadata: Some(HashMap::from([("Authorization".to_string(), "Bearer token".to_string())])),
+            messages,
+            ..Default::default()
+        };
+
+        let response = grpc::execute(call("test.v1.Greeter/SayHello", vec![
+            serde_json::json!({"name": "Ada", "sentAt": "2024-05-01T12:00:00Z"}),
+        ])).await.unwrap();
+        assert_eq!(response.kind, grpc::GrpcCallKind::Unary);
+        assert_eq!((response.status_code, response.status.as_str()), (0, "OK"));
+        assert_eq!(response.messages, vec![serde_json::json!({"message": "Hello, Ada"})]);
+        assert_eq!(find_header(&response.headers, "x-served-by"), Some("greeter"));
+        assert!(find_header(&response.trailers, "grpc-status").is_none());
+
+        let response = grpc::execute(call("test.v1.Greeter/Countdown", vec![
+            serde_json::json!({"name": "Lift-off", "count": 3}),
+        ])).await.unwrap();
+        assert_eq!(response.kind, grpc::GrpcCallKind::ServerStreaming);
+        let replies: Vec<_> = response.messages.iter().map(|m| m["message"].as_str().unwrap()).collect();
+        assert_eq!(replies, vec!["Lift-off 3", "Lift-off 2", "Lift-off 1"]);
+
+        let response = grpc::execute(call("test.v1.Greeter/Collect", vec![
+            serde_json::json!({"name": "Ada"}),
+            serde_json::json!({"name": "Grace"}),
+        ])).await.unwrap();
+        assert_eq!(response.kind, grpc::GrpcCallKind::ClientStreaming);
+        assert_eq!(response.messages, vec![serde_json::json!({"message": "Hello, Ada and Grace"})]);
+
+        // A failed call is a response carrying the status and its trailers
+        let response = grpc::execute(call("test.v1.Greeter/SayHello", vec![serde_json::json!({"name": ""})])).await.unwrap();
+        assert_eq!((response.status_code, response.status.as_str()), (3, "INVALID_ARGUMENT"));
+        assert_eq!(response.status_message, "name is required");
+        assert_eq!(find_header(&response.trailers, "x-field"), Some("name"));
+        assert!(response.messages.is_empty());
+
+        let error = grpc::execute(call("test.v1.Greeter/SayHello", Vec::new())).await.unwrap_err();
+        assert!(error.message.contains("exactly one request message"), "{}", error.message);
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        let error = grpc::execute(call("test.v1.Greeter/SayHello", vec![serde_json::json!({"nmae": "typo"})])).await.unwrap_err();
+        assert!(error.message.contains("test.v1.HelloRequest"), "{}", error.message);
+        let error = grpc::execute(call("test.v1.Greeter/Wave", vec![serde_json::json!({})])).await.unwrap_err();
+        assert!(error.message.contains("Method 'Wave' not found"), "{}", error.message);
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+    }
+
+    #[tokio::test]
+    async fn test_grpc_server_reflection() {
+        let url = grpc_server(&greeter_proto()).await;
+        let request = |method: &str| grpc::CosmoGrpcRequest {
+            url: url.clone(),
+            method: method.to_string(),
+            source: grpc::CosmoProtoSource::Reflection,
+            messages: vec![serde_json::json!({"name": "Ada"})],
+            ..Default::default()
+        };
+
+        let response = grpc::execute(request("test.v1.Greeter/SayHello")).await.unwrap();
+        assert_eq!(response.messages, vec![serde_json::json!({"message": "Hello, Ada"})]);
+
+        let services = grpc::describe(request("")).await.unwrap();
+        assert_eq!(services.iter().map(|s| s.name.as_str()).collect::<Vec<_>>(), vec!["test.v1.Greeter"]);
+        let methods: Vec<_> = services[0].methods.iter().map(|m| (m.full_name.as_str(), m.kind)).collect();
+        assert_eq!(methods, vec![
+            ("test.v1.Greeter/SayHello", grpc::GrpcCallKind::Unary),
+            ("test.v1.Greeter/Countdown", grpc::GrpcCallKind::ServerStreaming),
+            ("test.v1.Greeter/Collect", grpc::GrpcCallKind::ClientStreaming),
+        ]);
+        assert_eq!(services[0].methods[0].input_type, "test.v1.HelloRequest");
+        assert_eq!(services[0].methods[0].input_template["name"], "");
+
+        let error = grpc::execute(grpc::CosmoGrpcRequest {
+            url: "http://127.0.0.1:1".to_string(),
+            ..request("test.v1.Greeter/SayHello")
+        }).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused, "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn test_http_version_and_tls_details() {
+        let mut server = mockito::Server::new_async().await;
+        let _m = server.mock("GET", "/").with_body("ok").create_async().await;
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: server.url(),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.http_version, "HTTP/1.1");
+        assert_eq!(response.tls_version, None);
+        assert_eq!(response.cipher_suite, None);
+
+        let pki = test_pki();
+        let url = mtls_server(pki.server_config.clone()).await;
+        let tls = CosmoTlsSettings {
+            skip_verification: true,
+            client_certificates: vec![tls::CosmoClientCertificate {
+                host: "localhost".to_string(),
+                identity: tls::ClientIdentity::Pkcs12 {
+                    path: pki.dir.join("client.p12").to_string_lossy().into_owned(),
+                    password: "hunter2".to_string(),
+                },
+            }],
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: url.clone(),
+            tls: tls.clone(),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.http_version, "HTTP/1.1");
+        assert_eq!(response.tls_version.as_deref(), Some("TLSv1.3"));
+        assert!(response.cipher_suite.unwrap().starts_with("TLS13_"));
+
+        // Handshakes are remembered per pooled client, not per origin alone
+        let pool = ClientPool::default();
+        let settings = ClientSettings { tls, ..Default::default() };
+        let url = reqwest::Url::parse(&url).unwrap();
+        let negotiated = tls::NegotiatedTls { version: "TLSv1.3", cipher_suite: "TLS13_AES_128_GCM_SHA256".to_string() };
+        pool.client(&settings, None).await.unwrap().remember_negotiated(&url, negotiated.clone());
+        assert_eq!(pool.client(&settings, None).await.unwrap().negotiated_for(&url), Some(negotiated));
+        let other = ClientSettings { read_timeout_ms: Some(1000), ..settings };
+        assert_eq!(pool.client(&other, None).await.unwrap().negotiated_for(&url), None);
+        let _ = std::fs::remove_dir_all(&pki.dir);
+
+        // The gRPC server only speaks HTTP/2 over plain TCP
+        let proto_path = greeter_proto();
+        let url = grpc_server(&proto_path).await;
+        let send = |http_version: CosmoHttpVersion| do_execute_cosmo_request(None, CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/test.v1.Missing/Call", url),
+            http_version: Some(http_version),
+            ..Default::default()
+        });
+        let response = send(CosmoHttpVersion::Http2PriorKnowledge).await.unwrap();
+        assert_eq!(response.http_version, "HTTP/2");
+        assert!(send(CosmoHttpVersion::Http1Only).await.is_err());
+
+        let request: CosmoRequest = serde_json::from_str(r#"{"method": "GET", "url": "https://example.com", "http_version": "http1_only"}"#).unwrap();
+        assert_eq!(request.http_version, Some(CosmoHttpVersion::Http1Only));
+    }
+
+    #[tokio::test]
+    async fn test_compressed_bodies() {
+        use base64::Engine;
+        use std::io::Write;
+
+        let original = r#"{"message": "hello hello hello hello hello"}"#.repeat(20);
+        let gzip = {
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(original.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let deflate = {
+            let mut encoder = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(original.as_bytes()).unwrap();
+            encoder.finish().unwrap()
+        };
+        let brotli = |input: &[u8]| {
+            let mut encoder = brotli::CompressorWriter::new(Vec::new(), 4096, 5, 22);
+            encoder.write_all(input).unwrap();
+            encoder.into_inner()
+        };
+        let zstd = zstd::encode_all(original.as_bytes(), 3).unwrap();
+        // Applied in header order: gzip first, then brotli
+        let gzip_then_br = brotli(&gzip);
+
+        let mut server = mockito::Server::new_async().await;
+        let cases = [
+            ("gzip", gzip.clone()),
+            ("deflate", deflate),
+            ("br", brotli(original.as_bytes())),
+            ("zstd", zstd),
+            ("gzip, br", gzip_then_br),
+        ];
+        for (i, (encoding, body)) in cases.iter().enumerate() {
+            let _m = server
+                .mock("GET", format!("/{}", i).as_str())
+                .match_header("accept-encoding", compression::ACCEPT_ENCODING)
+                .with_header("content-type", "application/json")
+                .with_header("content-encoding", encoding)
+                .with_body(body)
+                .create_async()
+                .await;
+            let response = do_execute_cosmo_request(None, CosmoRequest {
+                method: "GET".to_string(),
+                url: format!("{}/{}", server.url(), i),
+                ..Default::default()
+            }).await.unwrap();
+            assert_eq!(response.body, original, "{}", encoding);
+            assert_eq!(response.content_encoding.as_deref(), Some(*encoding));
+            assert_eq!(response.compressed_size, body.len() as u64);
+            assert_eq!(response.decoded_size, original.len() as u64);
+            assert!(response.header_size > 0);
+        }
+
+        // Raw bodies are returned as received, and a caller's Accept-Encoding is kept
+        let _m = server
+            .mock("GET", "/raw")
+            .match_header("accept-encoding", "gzip")
+            .with_header("content-type", "application/json")
+            .with_header("content-encoding", "gzip")
+            .with_body(&gzip)
+            .create_async()
+            .await;
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/raw", server.url()),
+            headers: Some(HashMap::from([("Accept-Encoding".to_string(), "gzip".to_string())])),
+            raw_body: true,
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.content_encoding, None);
+        assert!(response.is_binary);
+        assert_eq!(response.mime_type.as_deref(), Some("application/json"));
+        assert_eq!(base64::engine::general_purpose::STANDARD.decode(&response.body_base64).unwrap(), gzip);
+        assert_eq!(response.compressed_size, gzip.len() as u64);
+        assert_eq!(response.decoded_size, gzip.len() as u64);
+
+        // Plain bodies report equal sizes; the header block is measured as HTTP/1.1
+        let _m = server
+            .mock("GET", "/plain")
+            .with_header("x-test", "1")
+            .with_body("ok")
+            .create_async()
+            .await;
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/plain", server.url()),
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!((response.compressed_size, response.decoded_size), (2, 2));
+        assert_eq!(response.content_encoding, None);
+        let expected: usize = response.headers.iter().map(|h| h.name.len() + h.value.len() + 4).sum::<usize>() + "HTTP/1.1 200 OK\r\n\r\n".len();
+        assert_eq!(response.header_size, expected as u64);
+
+        let _m = server
+            .mock("GET", "/corrupt")
+            .with_header("content-encoding", "gzip")
+            .with_body("not gzip")
+            .create_async()
+            .await;
+        let error = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/corrupt", server.url()),
+            ..Default::default()
+        }).await.unwrap_err();
+        assert!(error.message.contains("gzip"), "{}", error.message);
+        assert_eq!(error.error_type, CosmoErrorType::BodyDecodeError);
+    }
+
+    #[tokio::test]
+    async fn test_error_classification() {
+        use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+
+        let get = |url: String, tls: CosmoTlsSettings| do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            tls,
+            ..Default::default()
+        });
+
+        // Nothing listening on the port
+        let error = get(format!("http://{}/", refused_addr()), Default::default()).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused, "{:?}", error);
+        assert!(!error.causes.is_empty());
+
+        // The server hangs up without answering
+        let url = reply_server(|_| Some(String::new())).await;
+        let error = get(url, Default::default()).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionReset, "{:?}", error);
+
+        let error = get("http://cosmo-test.invalid/".to_string(), Default::default()).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::DnsError, "{:?}", error);
+
+        // Certificate problems
+        let pki = test_pki();
+        let url = mtls_server(pki.server_config.clone()).await;
+        let error = get(url.clone(), Default::default()).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::CertificateInvalid, "{:?}", error);
+        assert!(error.causes.iter().any(|cause| cause.contains("UnknownIssuer")), "{:?}", error.causes);
+
+        let trusted = CosmoTlsSettings {
+            ca_certificates: vec![pki.dir.join("ca.pem").to_string_lossy().into_owned()],
+            ..Default::default()
+        };
+        let by_ip = url.replace("localhost", "127.0.0.1");
+        let error = get(by_ip, trusted).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::HostnameMismatch, "{:?}", error);
+        let _ = std::fs::remove_dir_all(&pki.dir);
+
+        let ca_key = KeyPair::generate().unwrap();
+        let mut ca_params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let ca = ca_params.self_signed(&ca_key).unwrap();
+        let server_key = KeyPair::generate().unwrap();
+        let mut server_params = CertificateParams::new(vec!["localhost".to_string()]).unwrap();
+        server_params.not_before = rcgen::date_time_ymd(2020, 1, 1);
+        server_params.not_after = rcgen::date_time_ymd(2021, 1, 1);
+        let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+        let ca_path = std::env::temp_dir().join(format!("cosmo-ca-{}.pem", Uuid::new_v4()));
+        std::fs::write(&ca_path, ca.pem()).unwrap();
+        let config = rustls::ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions().unwrap()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![server.der().clone()],
+                rustls::pki_types::PrivateKeyDer::Pkcs8(server_key.serialize_der().into()),
+            ).unwrap();
+        let url = mtls_server(Arc::new(config)).await;
+        let error = get(url, CosmoTlsSettings {
+            ca_certificates: vec![ca_path.to_string_lossy().into_owned()],
+            ..Default::default()
+        }).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::CertificateExpired, "{:?}", error);
+        let _ = std::fs::remove_file(&ca_path);
+    }
+
+    /// Answers the first `failures` requests with a 503 carrying `retry_after`,
+    /// and the rest with `ok`. Returns the URL and the number of requests served.
+    async fn flaky_server(failures: usize, retry_after: Option<&'static str>) -> (String, Arc<std::sync::atomic::AtomicUsize>) {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = served.clone();
+        let url = reply_server(move |_| {
+            Some(if counter.fetch_add(1, Ordering::SeqCst) < failures {
+                let retry_after = retry_after.map(|value| format!("retry-after: {}\r\n", value)).unwrap_or_default();
+                format!("HTTP/1.1 503 Service Unavailable\r\n{}content-length: 0\r\nconnection: close\r\n\r\n", retry_after)
+            } else {
+                "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok".to_string()
+            })
+        })
+        .await;
+        (format!("{}/", url), served)
+    }
+
+    #[tokio::test]
+    async fn test_retry_policy() {
+        use std::sync::atomic::Ordering;
+
+        let quick = CosmoRetryPolicy { initial_delay_ms: 10, jitter: false, ..Default::default() };
+        let send = |url: String, method: &str, retry: CosmoRetryPolicy| do_execute_cosmo_request(None, CosmoRequest {
+            method: method.to_string(),
+            url,
+            retry: Some(retry),
+            ..Default::default()
+        });
+
+        let (url, served) = flaky_server(2, None).await;
+        let response = send(url, "GET", quick.clone()).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+        let log: Vec<_> = response.attempts.iter().map(|a| (a.attempt, a.status, a.delay_ms)).collect();
+        assert_eq!(log, vec![(1, Some(503), Some(10)), (2, Some(503), Some(20)), (3, Some(200), None)]);
+
+        // Out of attempts: the last response is returned as is
+        let (url, _) = flaky_server(5, None).await;
+        let response = send(url, "GET", CosmoRetryPolicy { max_attempts: 2, ..quick.clone() }).await.unwrap();
+        assert_eq!(response.status, 503);
+        assert_eq!(response.attempts.len(), 2);
+
+        // Retry-After is honored up to the maximum delay
+        let (url, _) = flaky_server(1, Some("120")).await;
+        let response = send(url, "GET", CosmoRetryPolicy { max_delay_ms: 30, ..quick.clone() }).await.unwrap();
+        assert_eq!(response.attempts[0].delay_ms, Some(30));
+
+        // POST is only retried when asked to
+        let (url, served) = flaky_server(2, None).await;
+        let response = send(url.clone(), "POST", quick.clone()).await.unwrap();
+        assert_eq!((response.status, response.attempts.len()), (503, 1));
+        let response = send(url, "POST", CosmoRetryPolicy { retry_non_idempotent: true, ..quick.clone() }).await.unwrap();
+        assert_eq!((response.status, response.attempts.len()), (200, 2));
+        assert_eq!(served.load(Ordering::SeqCst), 3);
+
+        // Errors are retried by type
+        let error = send(format!("http://{}/", refused_addr()), "GET", quick.clone()).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused);
+        assert!(error.message.contains("after 3 attempts"), "{}", error.message);
+        let log: Vec<_> = error.attempts.iter().map(|a| (a.attempt, a.error_type, a.delay_ms.is_some())).collect();
+        assert_eq!(log, vec![
+            (1, Some(CosmoErrorType::ConnectionRefused), true),
+            (2, Some(CosmoErrorType::ConnectionRefused), true),
+            (3, Some(CosmoErrorType::ConnectionRefused), false),
+        ]);
+
+        // Without a policy nothing is logged
+        let (url, _) = flaky_server(1, None).await;
+        let response = do_execute_cosmo_request(None, CosmoRequest {
+            method: "GET".to_string(),
+            url,
+            ..Default::default()
+        }).await.unwrap();
+        assert_eq!(response.status, 503);
+        assert!(response.attempts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_batch_runs_with_bounded_concurrency() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        // Answers with the request path after a short delay, recording how
+        // many requests were being served at once
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let active = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let (active_count, peak_count) = (active.clone(), peak.clone());
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let (active, peak) = (active_count.clone(), peak_count.clone());
+                tokio::spawn(async move {
+                    let mut buf = [0u8; 4096];
+                    let n = socket.read(&mut buf).await.unwrap_or(0);
+                    let request = String::from_utf8_lossy(&buf[..n]).into_owned();
+                    let path = request.split(' ').nth(1).unwrap_or("/").to_string();
+                    let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                    peak.fetch_max(now, Ordering::SeqCst);
+                    // Later requests answer sooner, so completion order differs from input order
+                    let delay = if path == "/0" { 150 } else { 40 };
+                    tokio::time::sleep(Duration::from_millis(delay)).await;
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    let reply = format!("HTTP/1.1 200 OK\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", path.len(), path);
+                    let _ = socket.write_all(reply.as_bytes()).await;
+                });
+            }
+        });
+
+        let mut requests: Vec<CosmoRequest> = (0..6)
+            .map(|i| CosmoRequest {
+                method: "GET".to_string(),
+                url: format!("http://127.0.0.1:{}/{}", port, i),
+                ..Default::default()
+            })
+            .collect();
+        requests[4].url = "not a url".to_string();
+        requests[2].request_id = Some("third".to_string());
+
+        let mut progress = Vec::new();
+        let in_flight = InFlightRequests::default();
+        let items = batch::run_batch(None, &in_flight, requests, 2, std::future::pending(), |p| progress.push(p)).await;
+
+        assert!(peak.load(Ordering::SeqCst) <= 2);
+        assert_eq!(items.iter().map(|item| item.index).collect::<Vec<_>>(), vec![0, 1, 2, 3, 4, 5]);
+        assert_eq!(items[0].response.as_ref().unwrap().body, "/0");
+        assert_eq!(items[5].response.as_ref().unwrap().body, "/5");
+        assert_eq!(items[2].request_id, "third");
+        assert!(matches!(items[4].error.as_ref().unwrap().error_type, CosmoErrorType::InvalidUrl));
+        assert!(items[4].response.is_none());
+
+        assert_eq!(progress.len(), 6);
+        assert_eq!(progress.iter().map(|p| p.completed).collect::<Vec<_>>(), vec![1, 2, 3, 4, 5, 6]);
+        assert!(progress.iter().all(|p| p.total == 6));
+        assert_ne!(progress[0].index, 0, "the slow first request should not finish first");
+        let failed = progress.iter().find(|p| p.index == 4).unwrap();
+        assert_eq!((failed.status, failed.error_type), (None, Some(CosmoErrorType::InvalidUrl)));
+    }
+
+    #[tokio::test]
+    async fn test_batch_cancellation() {
+        // Answers `/fast` right away and leaves every other request hanging
+        let url = reply_server(|request| {
+            request
+                .starts_with("GET /fast ")
+                .then(|| "HTTP/1.1 200 OK\r\ncontent-length: 2\r\nconnection: close\r\n\r\nok".to_string())
+        })
+        .await;
+        let request = |path: &str, id: &str| CosmoRequest {
+            method: "GET".to_string(),
+            url: format!("{}/{}", url, path),
+            request_id: Some(id.to_string()),
+            ..Default::default()
+        };
+
+        // Items are cancelled by their own id without affecting the others
+        let in_flight = InFlightRequests::default();
+        let cancel_slow = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            assert!(in_flight.cancel("slow"));
+        };
+        let requests = vec![request("fast", "fast"), request("slow", "slow")];
+        let (items, _) = tokio::join!(batch::run_batch(None, &in_flight, requests, 2, std::future::pending(), |_| {}), cancel_slow);
+        assert_eq!(items[0].response.as_ref().unwrap().body, "ok");
+        assert_eq!(items[1].error.as_ref().unwrap().error_type, CosmoErrorType::Cancelled);
+
+        // Cancelling the batch keeps finished items and cancels the rest,
+        // whether running or not started yet
+        let first_done = tokio::sync::Notify::new();
+        let requests = vec![request("fast", "a"), request("slow", "b"), request("slow", "c"), request("slow", "d")];
+        let items = batch::run_batch(None, &in_flight, requests, 2, first_done.notified(), |_| first_done.notify_one()).await;
+        assert_eq!(items.len(), 4);
+        assert_eq!(items[0].response.as_ref().unwrap().body, "ok");
+        for item in &items[1..] {
+            assert_eq!(item.error.as_ref().unwrap().error_type, CosmoErrorType::Cancelled, "{}", item.request_id);
+        }
+        assert!(!in_flight.cancel("b"), "cancelled items are no longer tracked");
+
+        // Ids shared within the batch are rejected rather than raced
+        let requests = vec![request("fast", "dup"), request("fast", "solo"), request("fast", "dup")];
+        let items = batch::run_batch(None, &in_flight, requests, 3, std::future::pending(), |_| {}).await;
+        assert_eq!(items[1].response.as_ref().unwrap().body, "ok");
+        for item in [&items[0], &items[2]] {
+            let error = item.error.as_ref().unwrap();
+            assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+            assert!(error.message.contains("'dup'"), "{}", error.message);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_load_test_report() {
+        use load_test::{CosmoLoadTest, LoadMode};
+
+        let load = |url: String, mode: LoadMode, duration_ms: Option<u64>, count: Option<u64>| CosmoLoadTest {
+            request: CosmoRequest { method: "GET".to_string(), url, ..Default::default() },
+            mode,
+            duration_ms,
+            count,
+            run_id: None,
+            metrics_channel_id: None,
+            metrics_interval_ms: Some(20),
+        };
+
+        let (url, _) = flaky_server(5, None).await;
+        let mut metrics = Vec::new();
+        let report = load_test::run(None, load(url, LoadMode::Concurrency { workers: 4 }, None, Some(20)), Default::default(), |m| metrics.push(m))
+            .await
+            .unwrap();
+        assert_eq!((report.sent, report.completed, report.failed), (20, 20, 0));
+        assert_eq!(report.status_codes, std::collections::BTreeMap::from([(200, 15), (503, 5)]));
+        assert!(!report.stopped);
+        let latency = &report.latency;
+        assert!(latency.min_ms > 0.0);
+        assert!(latency.p50_ms <= latency.p90_ms && latency.p90_ms <= latency.p95_ms && latency.p95_ms <= latency.p99_ms);
+        assert!(latency.p99_ms <= latency.max_ms);
+        assert_eq!(report.histogram.iter().map(|b| b.count).sum::<u64>(), 20);
+        let last = metrics.last().unwrap();
+        assert_eq!((last.sent, last.completed, last.in_flight), (20, 20, 0));
+
+        // Open model: requests are started on schedule even though all of them fail
+        let refused = format!("http://{}/", refused_addr());
+        let rate = LoadMode::Rate { per_second: 50.0, max_in_flight: None };
+        let report = load_test::run(None, load(refused, rate, None, Some(10)), Default::default(), |_| {})
+            .await
+            .unwrap();
+        assert_eq!((report.sent, report.failed), (10, 10));
+        // Ten starts 20 ms apart take at least nine intervals
+        assert!(report.duration_ms >= 180, "{}", report.duration_ms);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].error_type, CosmoErrorType::ConnectionRefused);
+        assert_eq!(report.errors[0].count, 10);
+        assert!(report.histogram.is_empty());
+        assert_eq!(report.latency.p99_ms, 0.0);
+
+        // Starts are skipped while the in-flight limit is reached; this server
+        // never answers, so only the first two start before the deadline
+        let (silent, _server) = silent_server().await;
+        let mut test = load(
+            format!("{}/", silent),
+            LoadMode::Rate { per_second: 200.0, max_in_flight: Some(2) },
+            Some(100),
+            None,
+        );
+        test.request.timeouts = Some(CosmoTimeouts { total_ms: Some(300), ..Default::default() });
+        let report = load_test::run(None, test, Default::default(), |_| {}).await.unwrap();
+        assert_eq!((report.sent, report.failed), (2, 2));
+        assert_eq!(report.errors[0].error_type, CosmoErrorType::TimeoutError);
+
+        // Stopping lets the requests in flight finish and still reports
+        let (url, _) = flaky_server(0, None).await;
+        let stop = Arc::new(tokio::sync::Notify::new());
+        stop.notify_one();
+        let report = load_test::run(None, load(url, LoadMode::Concurrency { workers: 2 }, Some(60_000), None), stop, |_| {})
+            .await
+            .unwrap();
+        assert!(report.stopped);
+        assert_eq!((report.sent, report.completed), (2, 2));
+
+        let error = load_test::run(None, load("http://127.0.0.1:1".to_string(), LoadMode::Concurrency { workers: 1 }, None, None), Default::default(), |_| {})
+            .await
+            .unwrap_err();
+        assert!(error.message.contains("duration or a request count"));
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+    }
+
+    #[test]
+    fn test_environment_scopes() {
+        let environments: environments::WorkspaceEnvironments = serde_json::from_str(r#"{
+            "globals": [{"key": "host", "value": "global"}, {"key": "token", "value": "g-token"}],
+            "environments": [
+                {"id": "dev", "name": "Dev", "variables": [{"key": "host", "value": "dev"}]},
+                {"id": "prod", "name": "Prod", "variables": [
+                    {"key": "host", "value": "prod"},
+                    {"key": "token", "value": "unused", "enabled": false}
+                ]}
+            ],
+            "active_environment_id": "dev",
+            "collections": {"users": [{"key": "host", "value": "collection"}, {"key": "path", "value": "/users"}]}
+        }"#).unwrap();
+
+        let active = environments.variables(None, Some("users"));
+        assert_eq!(active["host"], "dev");
+        assert_eq!(active["path"], "/users");
+        assert_eq!(active["token"], "g-token");
+
+        let prod = environments.variables(Some("prod"), None);
+        assert_eq!(prod["host"], "prod");
+        assert_eq!(prod["token"], "g-token");
+        assert!(!prod.contains_key("path"));
+
+        let no_environment = environments::WorkspaceEnvironments { active_environment_id: None, ..environments };
+        assert_eq!(no_environment.variables(None, Some("users"))["host"], "collection");
+    }
+
+    #[tokio::test]
+    async fn test_variable_substitution() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("POST", "/v1/users")
+            .match_header("authorization", "Bearer secret")
+            .match_body(r#"{"name": "Ada"}"#)
+            .with_status(201)
+            .create_async().await;
+
+        let variables = HashMap::from([
+            ("baseUrl".to_string(), format!("{}/{{{{version}}}}", server.url())),
+            ("version".to_string(), "v1".to_string()),
+            ("token".to_string(), "secret".to_string()),
+            ("name".to_string(), "Ada".to_string()),
+        ]);
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: "{{baseUrl}}/users".to_string(),
+            headers: Some(HashMap::from([("Authorization".to_string(), "Bearer {{ token }}".to_string())])),
+            body: Some(r#"{"name": "{{name}}"}"#.to_string().into()),
+            variables: variables.clone(),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.status, 201);
+        let resolved = response.resolved_request.unwrap();
+        assert_eq!(resolved.url, format!("{}/v1/users", server.url()));
+        assert_eq!(resolved.headers["Authorization"], "Bearer secret");
+        assert_eq!(resolved.body, Some(r#"{"name": "Ada"}"#.to_string().into()));
+
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: "{{baseUrl}}/{{missing}}".to_string(),
+            headers: Some(HashMap::from([("X-Api-Key".to_string(), "{{apiKey}}".to_string())])),
+            variables,
+            ..Default::default()
+        };
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        assert!(error.resolved_request.is_none());
+        assert!(error.message.contains("{{missing}} in URL"), "{}", error.message);
+        assert!(error.message.contains("{{apiKey}} in header X-Api-Key"), "{}", error.message);
+
+        // A failed send still echoes what was sent
+        let port = refused_addr().port();
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: "http://127.0.0.1:{{port}}/{{name}}".to_string(),
+            variables: HashMap::from([("port".to_string(), port.to_string()), ("name".to_string(), "Ada".to_string())]),
+            ..Default::default()
+        };
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ConnectionRefused);
+        assert_eq!(error.resolved_request.unwrap().url, format!("http://127.0.0.1:{}/Ada", port));
+    }
+
+    #[tokio::test]
+    async fn test_literal_braces_need_escaping() {
+        let mut server = Server::new_async().await;
+        let template = r#"{"template": "Hello {{name}}, {{#items}}{{.}}{{/items}}"}"#;
+        let _plain = server.mock("POST", "/render")
+            .match_body(template)
+            .with_status(200)
+            .create_async().await;
+
+        // Even without variables in scope, unknown placeholders are errors
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/render", server.url()),
+            body: Some(template.to_string().into()),
+            ..Default::default()
+        };
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        assert!(error.message.contains("{{name}} in body"), "{}", error.message);
+        assert!(error.message.contains("{{#items}} in body"), "{}", error.message);
+
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/render", server.url()),
+            body: Some(template.replace("{{", r"\{{").into()),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.resolved_request.unwrap().body, Some(template.to_string().into()));
+
+        // So are unknown placeholders inside variable values
+        let request = CosmoRequest {
+            method: "GET".to_string(),
+            url: "{{baseUrl}}/users".to_string(),
+            variables: HashMap::from([("baseUrl".to_string(), "{{host}}/api".to_string())]),
+            ..Default::default()
+        };
+        let error = do_execute_cosmo_request(None, request).await.unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        assert!(error.message.contains("{{host}} in URL"), "{}", error.message);
+
+        // `\{{` escapes a literal in requests and variable values alike
+        let _escaped = server.mock("POST", "/escaped")
+            .match_header("authorization", "Bearer a{{b}}c")
+            .match_body("{{name}} is Ada")
+            .with_status(200)
+            .create_async().await;
+        let request = CosmoRequest {
+            method: "POST".to_string(),
+            url: format!("{}/escaped", server.url()),
+            headers: Some(HashMap::from([("Authorization".to_string(), "Bearer {{token}}".to_string())])),
+            body: Some(r"\{{name}} is {{name}}".to_string().into()),
+            variables: HashMap::from([
+                ("token".to_string(), r"a\{{b}}c".to_string()),
+                ("name".to_string(), "Ada".to_string()),
+            ]),
+            ..Default::default()
+        };
+        let response = do_execute_cosmo_request(None, request).await.unwrap();
+        assert_eq!(response.status, 200);
+    }
+
+    #[tokio::test]
+    async fn test_dynamic_template_helpers() {
+        let mut server = Server::new_async().await;
+        let _m = server.mock("GET", "/f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8")
+            .match_query(mockito::Matcher::UrlEncoded("q".to_string(), "a b&c".to_string()))
+            .match_header("x-digest", "cVprK05rY0dnV3E2UGlWeGVGRENiSnpRMkowPQ==")
+            .with_status(200)
+            .create_async().await;
+        let request = |url: &str, seed: Option<u64>| CosmoRequest {
+            method: "GET".to_string(),
+            url: url.to_string(),
+            headers: Some(HashMap::from([
+                ("X-Digest".to_string(), "{{$base64($sha1('abc', 'base64'))}}".to_string()),
+                ("X-Random".to_string(), "{{$randomInt(5, 9)}}/{{$randomString(12)}}/{{$uuid}}".to_string()),
+            ])),
+            variables: HashMap::from([
+                ("baseUrl".to_string(), server.url()),
+                ("secret".to_string(), "key".to_string()),
+            ]),
+            template_seed: seed,
+            ..Default::default()
+        };
+
+        // Helpers take variables and other helpers as arguments, and a seed
+        // reproduces the random values
+        let url = r#"{{baseUrl}}/{{ $hmacSha256(secret, "The quick brown fox jumps over the lazy dog") }}?q={{$urlEncode('a b&c')}}"#;
+        let mut random = Vec::new();
+        for seed in [42, 42, 43] {
+            let response = do_execute_cosmo_request(None, request(url, Some(seed))).await.unwrap();
+            assert_eq!(response.status, 200);
+            random.push(response.resolved_request.unwrap().headers["X-Random"].clone());
+        }
+        assert_eq!(random[0], random[1]);
+        assert_ne!(random[0], random[2]);
+
+        let error = do_execute_cosmo_request(None, request("{{$randomInt(9, 5)}}/{{$nope}}/{{$sha256(missing)}}/{{$base64('x'}}", None))
+            .await
+            .unwrap_err();
+        assert_eq!(error.error_type, CosmoErrorType::ValidationError);
+        assert!(error.message.contains("{{$randomInt(9, 5)}} in URL ($randomInt needs min <= max, got 9 and 5)"), "{}", error.message);
+        assert!(error.message.contains("{{$nope}} in URL (unknown helper $nope)"), "{}", error.message);
+        assert!(error.message.contains("{{missing}} in URL"), "{}", error.message);
+        assert!(error.message.contains("{{$base64('x'}} in URL (expected ',' or ')' in arguments of $base64)"), "{}", error.message);
+    }
+
+    #[test]
+    fn test_workspace_timeout_defaults() {
+        let prefs = preferences::parse_workspace_preferences(
+            r#"{"request_timeouts": {"connect_ms": 500, "total_ms": 3000}, "theme": "dark"}"#,
+        );
+        let request_timeouts = CosmoTimeouts { total_ms: Some(1000), ..Default::default() };
+        let merged = request_timeouts.or(prefs.request_timeouts);
+
+        assert_eq!(merged.connect_ms, Some(500));
+        assert_eq!(merged.read_ms, None);
+        assert_eq!(merged.total_ms, Some(1000));
     }
 }
@@ -0,0 +1,402 @@
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures_util::stream::FuturesUnordered;
+use futures_util::StreamExt;
+use hdrhistogram::Histogram;
+use serde::{Deserialize, Serialize};
+use tauri::Emitter;
+use tokio::sync::Notify;
+use tokio::time::MissedTickBehavior;
+use uuid::Uuid;
+
+use crate::{CosmoError, CosmoErrorType, CosmoRequest, CosmoResponse};
+
+/// How requests are started during a load test.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum LoadMode {
+    /// Start `per_second` requests every second, however long they take.
+    /// Starts that are due while `max_in_flight` requests are still running
+    /// are skipped rather than made up later.
+    Rate {
+        per_second: f64,
+        /// Defaults to `DEFAULT_MAX_IN_FLIGHT`
+        #[serde(default)]
+        max_in_flight: Option<usize>,
+    },
+    /// Keep `workers` requests in flight, starting the next as soon as one completes
+    Concurrency { workers: usize },
+}
+
+/// A load test of a single request.
+#[derive(Debug, Deserialize)]
+pub struct CosmoLoadTest {
+    pub request: CosmoRequest,
+    pub mode: LoadMode,
+    /// Stop starting requests after this long
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+    /// Stop after starting this many requests; with `duration_ms` too,
+    /// whichever limit is reached first ends the test
+    #[serde(default)]
+    pub count: Option<u64>,
+    /// Id usable with `stop_load_test`; generated when absent
+    #[serde(default)]
+    pub run_id: Option<String>,
+    /// Channel to emit `LoadMetrics` on, so the caller can subscribe before
+    /// starting; generated when absent
+    #[serde(default)]
+    pub metrics_channel_id: Option<String>,
+    /// How often metrics are emitted; every second when absent
+    #[serde(default)]
+    pub metrics_interval_ms: Option<u64>,
+}
+
+/// Live metrics, emitted periodically while a load test runs and once at the end.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadMetrics {
+    pub elapsed_ms: u64,
+    pub sent: u64,
+    /// Requests that received a response
+    pub completed: u64,
+    /// Requests that failed without a response
+    pub failed: u64,
+    pub in_flight: u64,
+    /// Requests finished per second since the previous metrics
+    pub throughput_rps: f64,
+    /// Share of the requests finished since the previous metrics that failed
+    pub error_rate: f64,
+    /// Latency percentiles of all responses so far
+    pub p50_ms: f64,
+    pub p99_ms: f64,
+}
+
+/// Final report of a load test.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadReport {
+    pub run_id: String,
+    pub metrics_channel_id: String,
+    pub sent: u64,
+    /// Requests that received a response, whatever its status
+    pub completed: u64,
+    /// Requests that failed without a response
+    pub failed: u64,
+    /// Whether the test was ended early with `stop_load_test`
+    pub stopped: bool,
+    pub duration_ms: u64,
+    /// Requests finished per second over the whole test
+    pub throughput_rps: f64,
+    /// Latency of the requests that received a response
+    pub latency: LatencySummary,
+    /// Response latencies in buckets that double in width from 1 ms
+    pub histogram: Vec<HistogramBucket>,
+    /// Responses per status code
+    pub status_codes: BTreeMap<u16, u64>,
+    /// Failures per error type, most frequent first
+    pub errors: Vec<LoadErrorCount>,
+}
+
+/// Latency statistics in milliseconds, with microsecond precision; all zero
+/// when no response was received.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct LatencySummary {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub max_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p95_ms: f64,
+    pub p99_ms: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HistogramBucket {
+    /// Upper bound of the bucket, inclusive
+    pub up_to_ms: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoadErrorCount {
+    pub error_type: CosmoErrorType,
+    pub count: u64,
+    /// Message of the first failure of this type
+    pub example: String,
+}
+
+const DEFAULT_METRICS_INTERVAL_MS: u64 = 1000;
+/// Most requests a rate mode test keeps in flight unless it sets its own limit
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 1000;
+
+/// Results collected while a load test runs.
+struct LoadStats {
+    /// Response latencies in microseconds
+    latencies: Histogram<u64>,
+    status_codes: BTreeMap<u16, u64>,
+    errors: HashMap<CosmoErrorType, (u64, String)>,
+    completed: u64,
+    failed: u64,
+    /// Totals when metrics were last emitted
+    reported: (u64, u64, Instant),
+}
+
+impl LoadStats {
+    fn new(started: Instant) -> LoadStats {
+        LoadStats {
+            // 1 µs to 1 hour at 3 significant digits
+            latencies: Histogram::new_with_bounds(1, 3_600_000_000, 3).expect("valid histogram bounds"),
+            status_codes: BTreeMap::new(),
+            errors: HashMap::new(),
+            completed: 0,
+            failed: 0,
+            reported: (0, 0, started),
+        }
+    }
+
+    fn record(&mut self, latency: Duration, result: Result<CosmoResponse, CosmoError>) {
+        match result {
+            Ok(response) => {
+                self.completed += 1;
+                self.latencies.saturating_record(latency.as_micros().max(1) as u64);
+                *self.status_codes.entry(response.status).or_default() += 1;
+            }
+            Err(error) => {
+                self.failed += 1;
+                self.errors.entry(error.error_type).or_insert((0, error.message)).0 += 1;
+            }
+        }
+    }
+
+    fn percentile_ms(&self, quantile: f64) -> f64 {
+        if self.latencies.is_empty() {
+            return 0.0;
+        }
+        self.latencies.value_at_quantile(quantile) as f64 / 1000.0
+    }
+
+    fn metrics(&mut self, started: Instant, sent: u64) -> LoadMetrics {
+        let now = Instant::now();
+        let (completed, failed, since) = self.reported;
+        let finished = (self.completed - completed) + (self.failed - failed);
+        let window = now.duration_since(since).as_secs_f64();
+        self.reported = (self.completed, self.failed, now);
+        LoadMetrics {
+            elapsed_ms: now.duration_since(started).as_millis() as u64,
+            sent,
+            completed: self.completed,
+            failed: self.failed,
+            in_flight: sent - self.completed - self.failed,
+            throughput_rps: if window > 0.0 { finished as f64 / window } else { 0.0 },
+            error_rate: if finished > 0 { (self.failed - failed) as f64 / finished as f64 } else { 0.0 },
+            p50_ms: self.percentile_ms(0.5),
+            p99_ms: self.percentile_ms(0.99),
+        }
+    }
+
+    fn report(self, run_id: String, metrics_channel_id: String, sent: u64, stopped: bool, elapsed: Duration) -> LoadReport {
+        let latency = if self.latencies.is_empty() {
+            LatencySummary::default()
+        } else {
+            LatencySummary {
+                min_ms: self.latencies.min() as f64 / 1000.0,
+                mean_ms: self.latencies.mean() / 1000.0,
+                max_ms: self.latencies.max() as f64 / 1000.0,
+                p50_ms: self.percentile_ms(0.5),
+                p90_ms: self.percentile_ms(0.9),
+                p95_ms: self.percentile_ms(0.95),
+                p99_ms: self.percentile_ms(0.99),
+            }
+        };
+        let histogram = if self.latencies.is_empty() {
+            Vec::new()
+        } else {
+            self.latencies
+                .iter_log(1000, 2.0)
+                .map(|bucket| HistogramBucket {
+                    up_to_ms: bucket.value_iterated_to() as f64 / 1000.0,
+                    count: bucket.count_since_last_iteration(),
+                })
+                .collect()
+        };
+        let mut errors: Vec<LoadErrorCount> = self
+            .errors
+            .into_iter()
+            .map(|(error_type, (count, example))| LoadErrorCount { error_type, count, example })
+            .collect();
+        errors.sort_by_key(|error| std::cmp::Reverse(error.count));
+
+        let finished = self.completed + self.failed;
+        let seconds = elapsed.as_secs_f64();
+        LoadReport {
+            run_id,
+            metrics_channel_id,
+            sent,
+            completed: self.completed,
+            failed: self.failed,
+            stopped,
+            duration_ms: elapsed.as_millis() as u64,
+            throughput_rps: if seconds > 0.0 { finished as f64 / seconds } else { 0.0 },
+            latency,
+            histogram,
+            status_codes: self.status_codes,
+            errors,
+        }
+    }
+}
+
+fn invalid_test(message: &str) -> CosmoError {
+    CosmoError::new(CosmoErrorType::ValidationError, format!("Invalid load test: {}", message))
+}
+
+/// Runs a load test until its duration or count is reached or `stop` is
+/// notified, calling `on_metrics` periodically and once more at the end.
+/// Requests already started when the test ends are waited for.
+pub async fn run<M>(app_handle: Option<tauri::AppHandle>, test: CosmoLoadTest, stop: Arc<Notify>, mut on_metrics: M) -> Result<LoadReport, CosmoError>
+where
+    M: FnMut(LoadMetrics),
+{
+    match test.mode {
+        LoadMode::Rate { per_second, .. } if !(per_second > 0.0 && per_second.is_finite()) => {
+            return Err(invalid_test("the rate must be a positive number of requests per second"));
+        }
+        LoadMode::Rate { max_in_flight: Some(0), .. } => return Err(invalid_test("at least one request must be allowed in flight")),
+        LoadMode::Concurrency { workers: 0 } => return Err(invalid_test("at least one worker is needed")),
+        _ => {}
+    }
+    if test.duration_ms.is_none() && test.count.is_none() {
+        return Err(invalid_test("a duration or a request count is needed"));
+    }
+
+    let run_id = test.run_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let metrics_channel_id = test.metrics_channel_id.unwrap_or_else(|| Uuid::new_v4().to_string());
+    let mut request = test.request;
+    // Every copy is sent on its own; ids only matter for cancelling single requests
+    request.request_id = None;
+    // Each request runs as its own task, so latencies measure the server
+    // rather than how long the request waited to be polled
+    let fire = || {
+        let app_handle = app_handle.clone();
+        let request = request.clone();
+        let started = Instant::now();
+        let task = tokio::spawn(crate::do_execute_cosmo_request(app_handle, request));
+        async move {
+            let result = task.await.unwrap_or_else(|e| Err(CosmoError::new(
+                CosmoErrorType::UnknownError,
+                format!("Load test request did not finish: {}", e),
+            )));
+            (started.elapsed(), result)
+        }
+    };
+
+    let started = Instant::now();
+    let deadline = test.duration_ms.map(|ms| tokio::time::Instant::now() + Duration::from_millis(ms));
+    let has_budget = |sent: u64| {
+        test.count.map_or(true, |count| sent < count) && deadline.map_or(true, |deadline| tokio::time::Instant::now() < deadline)
+    };
+    let mut stats = LoadStats::new(started);
+    let mut in_flight = FuturesUnordered::new();
+    let mut sent = 0u64;
+    let mut stopped = false;
+
+    let mut rate_ticker = match test.mode {
+        LoadMode::Rate { per_second, .. } => {
+            let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / per_second));
+            // After a stall, keep to the schedule instead of bursting to catch up
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            Some(ticker)
+        }
+        LoadMode::Concurrency { .. } => None,
+    };
+    let (workers, max_in_flight) = match test.mode {
+        LoadMode::Concurrency { workers } => (workers, workers),
+        LoadMode::Rate { max_in_flight, .. } => (0, max_in_flight.unwrap_or(DEFAULT_MAX_IN_FLIGHT)),
+    };
+    let metrics_every = Duration::from_millis(test.metrics_interval_ms.unwrap_or(DEFAULT_METRICS_INTERVAL_MS).max(1));
+    let mut metrics_ticker = tokio::time::interval_at(tokio::time::Instant::now() + metrics_every, metrics_every);
+    metrics_ticker.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    let deadline_sleep = tokio::time::sleep_until(deadline.unwrap_or_else(|| tokio::time::Instant::now() + Duration::from_secs(86_400 * 365)));
+    tokio::pin!(deadline_sleep);
+
+    loop {
+        let issuing = !stopped && has_budget(sent);
+        while issuing && in_flight.len() < workers && has_budget(sent) {
+            in_flight.push(fire());
+            sent += 1;
+        }
+        if !issuing && in_flight.is_empty() {
+            break;
+        }
+
+        tokio::select! {
+            Some((latency, result)) = in_flight.next(), if !in_flight.is_empty() => stats.record(latency, result),
+            _ = async { rate_ticker.as_mut().unwrap().tick().await }, if issuing && rate_ticker.is_some() && in_flight.len() < max_in_flight => {
+                in_flight.push(fire());
+                sent += 1;
+            }
+            _ = metrics_ticker.tick() => on_metrics(stats.metrics(started, sent)),
+            _ = &mut deadline_sleep, if issuing && deadline.is_some() => {}
+            _ = stop.notified(), if !stopped => stopped = true,
+        }
+    }
+
+    on_metrics(stats.metrics(started, sent));
+    Ok(stats.report(run_id, metrics_channel_id, sent, stopped, started.elapsed()))
+}
+
+/// Load tests in progress keyed by run id, managed as Tauri state so they can
+/// be stopped early.
+#[derive(Default)]
+pub struct LoadTests {
+    runs: Mutex<HashMap<String, Arc<Notify>>>,
+}
+
+/// Fires a request repeatedly at a fixed rate or concurrency, emitting
+/// `LoadMetrics` on the metrics channel while it runs, and returns the final
+/// report. The request gets the workspace preferences as for
+/// `execute_cosmo_request`.
+#[tauri::command]
+pub async fn start_load_test(
+    app_handle: tauri::AppHandle,
+    load_tests: tauri::State<'_, LoadTests>,
+    test: CosmoLoadTest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<LoadReport, CosmoError> {
+    let mut test = test;
+    crate::apply_workspace_preferences(&app_handle, &mut test.request, user_id, workspace_id).await;
+    let run_id = test.run_id.get_or_insert_with(|| Uuid::new_v4().to_string()).clone();
+    let channel_id = test.metrics_channel_id.get_or_insert_with(|| Uuid::new_v4().to_string()).clone();
+
+    let stop = Arc::new(Notify::new());
+    {
+        let mut runs = load_tests.runs.lock().unwrap();
+        if runs.contains_key(&run_id) {
+            return Err(invalid_test(&format!("run '{}' is already in progress", run_id)));
+        }
+        runs.insert(run_id.clone(), stop.clone());
+    }
+
+    let emit = |metrics: LoadMetrics| {
+        if let Err(e) = app_handle.emit(&channel_id, metrics) {
+            log::error!("Failed to emit load metrics on {}: {}", channel_id, e);
+        }
+    };
+    let report = run(Some(app_handle.clone()), test, stop, emit).await;
+    load_tests.runs.lock().unwrap().remove(&run_id);
+    report
+}
+
+/// Stops starting new requests in a load test; it returns its report once
+/// the requests in flight complete. Returns `false` if no such run is in progress.
+#[tauri::command]
+pub async fn stop_load_test(load_tests: tauri::State<'_, LoadTests>, run_id: String) -> Result<bool, String> {
+    match load_tests.runs.lock().unwrap().get(&run_id) {
+        Some(stop) => {
+            stop.notify_one();
+            Ok(true)
+        }
+        None => Ok(false),
+    }
+}
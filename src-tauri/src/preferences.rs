@@ -0,0 +1,82 @@
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tauri::Manager;
+
+use crate::proxy::CosmoProxy;
+use crate::stream::CosmoStreamRule;
+use crate::tls::CosmoTlsSettings;
+use crate::CosmoTimeouts;
+
+/// Request engine defaults stored per workspace.
+///
+/// Persisted as `preferences.json` next to `collections.json`. Unknown keys are
+/// ignored so the frontend can keep UI-only settings in the same file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WorkspacePreferences {
+    /// Timeouts applied to requests that don't set their own
+    pub request_timeouts: CosmoTimeouts,
+    /// Proxy used by requests that don't set their own
+    pub proxy: Option<CosmoProxy>,
+    /// Extra trusted CAs, verification toggle and client certificates
+    pub tls: CosmoTlsSettings,
+    /// Content types to stream, checked before the built-in SSE and NDJSON ones
+    pub stream_rules: Vec<CosmoStreamRule>,
+}
+
+/// Identifies one of a user's workspaces.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WorkspaceScope {
+    pub user_id: String,
+    pub workspace_id: String,
+}
+
+impl WorkspaceScope {
+    pub fn new(user_id: String, workspace_id: String) -> WorkspaceScope {
+        WorkspaceScope { user_id, workspace_id }
+    }
+
+    /// Resolves the workspace's data directory.
+    pub fn dir(&self, app_handle: &tauri::AppHandle) -> Result<PathBuf, String> {
+        workspace_dir(app_handle, &self.user_id, &self.workspace_id)
+    }
+}
+
+/// Resolves the data directory for a user's workspace.
+pub fn workspace_dir(
+    app_handle: &tauri::AppHandle,
+    user_id: &str,
+    workspace_id: &str,
+) -> Result<PathBuf, String> {
+    let app_dir = app_handle.path().app_data_dir().map_err(|e: tauri::Error| e.to_string())?;
+    Ok(app_dir.join("users").join(user_id).join("workspaces").join(workspace_id))
+}
+
+/// Loads the typed workspace preferences, falling back to defaults when the
+/// file is missing or cannot be parsed.
+pub fn load_workspace_preferences(
+    app_handle: &tauri::AppHandle,
+    user_id: &str,
+    workspace_id: &str,
+) -> WorkspacePreferences {
+    let file_path = match workspace_dir(app_handle, user_id, workspace_id) {
+        Ok(dir) => dir.join("preferences.json"),
+        Err(e) => {
+            log::warn!("Failed to resolve workspace directory: {}", e);
+            return WorkspacePreferences::default();
+        }
+    };
+
+    match std::fs::read_to_string(&file_path) {
+        Ok(content) => parse_workspace_preferences(&content),
+        Err(_) => WorkspacePreferences::default(),
+    }
+}
+
+/// Parses workspace preferences JSON, logging and defaulting on malformed input.
+pub fn parse_workspace_preferences(content: &str) -> WorkspacePreferences {
+    serde_json::from_str(content).unwrap_or_else(|e| {
+        log::warn!("Ignoring malformed workspace preferences: {}", e);
+        WorkspacePreferences::default()
+    })
+}
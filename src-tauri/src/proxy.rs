@@ -0,0 +1,105 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{CosmoError, CosmoErrorType};
+
+/// Outbound proxy configuration, set per workspace or per request.
+///
+/// A request-level proxy replaces the workspace one entirely; sending one with
+/// `enabled: false` forces a direct connection, bypassing the workspace proxy
+/// and any `HTTP(S)_PROXY` environment variables.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoProxy {
+    pub enabled: bool,
+    /// Proxy URL with an `http://`, `https://`, `socks5://` or `socks5h://` scheme
+    pub url: String,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Hosts reached directly, in `NO_PROXY` syntax (`example.com`, `.internal`,
+    /// `10.0.0.0/8`, or `*` for everything)
+    pub no_proxy: Vec<String>,
+}
+
+impl Default for CosmoProxy {
+    fn default() -> Self {
+        CosmoProxy {
+            enabled: true,
+            url: String::new(),
+            username: None,
+            password: None,
+            no_proxy: Vec::new(),
+        }
+    }
+}
+
+impl CosmoProxy {
+    /// Builds the reqwest proxy, routing every scheme through `url`.
+    pub fn to_reqwest(&self) -> Result<reqwest::Proxy, CosmoError> {
+        let mut proxy = reqwest::Proxy::all(self.url.trim()).map_err(|e| CosmoError::new(
+            CosmoErrorType::ProxyError,
+            format!("Invalid proxy URL '{}': {}", self.url, e),
+        ))?;
+
+        if let Some(username) = &self.username {
+            proxy = proxy.basic_auth(username, self.password.as_deref().unwrap_or(""));
+        }
+        if !self.no_proxy.is_empty() {
+            proxy = proxy.no_proxy(reqwest::NoProxy::from_string(&self.no_proxy.join(",")));
+        }
+        Ok(proxy)
+    }
+
+    /// The proxy URL with any embedded password masked, for error messages.
+    pub fn redacted_url(&self) -> String {
+        match reqwest::Url::parse(self.url.trim()) {
+            Ok(mut url) if url.password().is_some() => {
+                let _ = url.set_password(Some("***"));
+                url.to_string()
+            }
+            _ => self.url.clone(),
+        }
+    }
+
+    /// Whether a request to `url` is routed through this proxy.
+    pub fn applies_to(&self, url: &reqwest::Url) -> bool {
+        if !self.enabled {
+            return false;
+        }
+        let host = match url.host_str() {
+            Some(host) => host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase(),
+            None => return true,
+        };
+
+        !self.no_proxy.iter().any(|entry| {
+            let entry = entry.trim().to_ascii_lowercase();
+            if entry == "*" {
+                return true;
+            }
+            if let (Ok(ip), Some((network, prefix))) = (host.parse::<std::net::IpAddr>(), entry.split_once('/')) {
+                return in_subnet(ip, network, prefix);
+            }
+            let domain = entry.trim_start_matches('.');
+            !domain.is_empty() && (host == domain || host.ends_with(&format!(".{}", domain)))
+        })
+    }
+}
+
+/// Whether `ip` lies in the CIDR block `network/prefix`.
+fn in_subnet(ip: std::net::IpAddr, network: &str, prefix: &str) -> bool {
+    use std::net::IpAddr;
+    let (network, prefix) = match (network.parse::<IpAddr>(), prefix.parse::<u32>()) {
+        (Ok(network), Ok(prefix)) => (network, prefix),
+        _ => return false,
+    };
+    match (ip, network) {
+        (IpAddr::V4(ip), IpAddr::V4(network)) if prefix <= 32 => {
+            let mask = u32::MAX.checked_shl(32 - prefix).unwrap_or(0);
+            u32::from(ip) & mask == u32::from(network) & mask
+        }
+        (IpAddr::V6(ip), IpAddr::V6(network)) if prefix <= 128 => {
+            let mask = u128::MAX.checked_shl(128 - prefix).unwrap_or(0);
+            u128::from(ip) & mask == u128::from(network) & mask
+        }
+        _ => false,
+    }
+}
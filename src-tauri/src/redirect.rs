@@ -0,0 +1,80 @@
+use reqwest::{Method, StatusCode, Url};
+use serde::{Deserialize, Serialize};
+
+use crate::CosmoHeader;
+
+/// How a request handles redirect responses.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoRedirectPolicy {
+    /// Follow redirects; when `false` the 3xx response itself is returned
+    pub follow: bool,
+    /// Number of redirects followed before failing
+    pub max_redirects: u32,
+    /// Keep `Authorization`, `Cookie` and `Proxy-Authorization` headers when
+    /// redirected to a different origin
+    pub keep_auth_across_origins: bool,
+}
+
+impl Default for CosmoRedirectPolicy {
+    fn default() -> Self {
+        CosmoRedirectPolicy {
+            follow: true,
+            max_redirects: 10,
+            keep_auth_across_origins: false,
+        }
+    }
+}
+
+/// One redirect response on the way to the final response.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CosmoRedirect {
+    /// URL that was requested
+    pub url: String,
+    pub status: u16,
+    /// Headers of the redirect response, including `Location`
+    pub headers: Vec<CosmoHeader>,
+}
+
+/// Where a redirect leads and how the follow-up request is sent.
+pub struct Hop {
+    pub url: Url,
+    pub method: Method,
+    /// The request body is dropped, as when a POST is redirected with 303
+    pub drops_body: bool,
+    /// The new URL is on a different origin
+    pub crosses_origin: bool,
+}
+
+/// Works out the follow-up request for a redirect response, or `None` when
+/// `status` is not a redirect or `location` is missing or invalid.
+pub fn next_hop(url: &Url, method: &Method, status: StatusCode, location: Option<&str>) -> Option<Hop> {
+    let keeps_method = match status {
+        StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND => *method != Method::POST,
+        StatusCode::SEE_OTHER => *method == Method::HEAD,
+        StatusCode::TEMPORARY_REDIRECT | StatusCode::PERMANENT_REDIRECT => true,
+        _ => return None,
+    };
+    let next = url.join(location?.trim()).ok()?;
+
+    Some(Hop {
+        crosses_origin: next.origin() != url.origin(),
+        url: next,
+        method: if keeps_method { method.clone() } else { Method::GET },
+        drops_body: !keeps_method,
+    })
+}
+
+/// Whether a request header carries credentials.
+pub fn is_auth_header(name: &str) -> bool {
+    ["authorization", "cookie", "proxy-authorization"]
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(name))
+}
+
+/// Whether a request header describes the body and goes away with it.
+pub fn is_body_header(name: &str) -> bool {
+    ["content-type", "content-length", "content-encoding", "content-language", "content-location"]
+        .iter()
+        .any(|h| h.eq_ignore_ascii_case(name))
+}
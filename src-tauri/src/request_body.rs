@@ -0,0 +1,247 @@
+use reqwest::multipart::{Form, Part};
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::graphql;
+use crate::{CosmoError, CosmoErrorType};
+
+/// Request body sent from the frontend.
+///
+/// A bare JSON string is accepted as a `raw` body without a content type.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CosmoBody {
+    /// Text sent as-is
+    Raw {
+        content: String,
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    /// `application/x-www-form-urlencoded` fields, in order
+    Urlencoded { fields: Vec<CosmoFormField> },
+    /// `multipart/form-data` with text fields and files
+    Multipart { parts: Vec<CosmoMultipartPart> },
+    /// Contents of a file, streamed from disk when the request is sent
+    File {
+        path: String,
+        /// Defaults to a type guessed from the file extension
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    /// Arbitrary bytes, base64 encoded
+    Base64 {
+        data: String,
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    /// A GraphQL operation, sent as `application/json`
+    Graphql {
+        query: String,
+        #[serde(default)]
+        variables: Option<serde_json::Value>,
+        #[serde(default)]
+        operation_name: Option<String>,
+    },
+}
+
+impl From<String> for CosmoBody {
+    fn from(content: String) -> Self {
+        CosmoBody::Raw { content, content_type: None }
+    }
+}
+
+/// A name/value pair of a urlencoded form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmoFormField {
+    pub name: String,
+    pub value: String,
+}
+
+/// One part of a multipart form.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CosmoMultipartPart {
+    Text {
+        name: String,
+        value: String,
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+    File {
+        name: String,
+        path: String,
+        /// Filename reported to the server; defaults to the file's own name
+        #[serde(default)]
+        filename: Option<String>,
+        /// Defaults to a type guessed from the file extension
+        #[serde(default)]
+        content_type: Option<String>,
+    },
+}
+
+impl CosmoBody {
+    /// The content type the body is sent with unless the request sets its
+    /// own. Multipart bodies get theirs, with the boundary, from reqwest.
+    pub fn content_type(&self) -> Option<String> {
+        match self {
+            CosmoBody::Raw { content_type, .. } | CosmoBody::Base64 { content_type, .. } => content_type.clone(),
+            CosmoBody::Urlencoded { .. } => Some("application/x-www-form-urlencoded".to_string()),
+            CosmoBody::Multipart { .. } => None,
+            CosmoBody::File { path, content_type } => Some(content_type.clone().unwrap_or_else(|| guess_mime(path))),
+            CosmoBody::Graphql { .. } => Some("application/json".to_string()),
+        }
+    }
+
+    /// Sets the body on a request about to be sent. Files are streamed from
+    /// disk, and opened again for every send so redirects and retries can
+    /// repeat the body.
+    pub async fn attach(&self, request: reqwest::RequestBuilder) -> Result<reqwest::RequestBuilder, CosmoError> {
+        Ok(match self {
+            CosmoBody::Raw { content, .. } => request.body(content.clone()),
+            CosmoBody::Urlencoded { fields } => {
+                let encoded = url::form_urlencoded::Serializer::new(String::new())
+                    .extend_pairs(fields.iter().map(|f| (&f.name, &f.value)))
+                    .finish();
+                request.body(encoded)
+            }
+            CosmoBody::Multipart { parts } => request.multipart(multipart_form(parts).await?),
+            CosmoBody::File { path, .. } => {
+                let file = open_file(path).await?;
+                let len = file.metadata().await.map_err(|e| file_error(path, e))?.len();
+                request.header(reqwest::header::CONTENT_LENGTH, len).body(file)
+            }
+            CosmoBody::Base64 { data, .. } => {
+                use base64::Engine;
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(data.trim())
+                    .map_err(|e| CosmoError::new(
+                        CosmoErrorType::ValidationError,
+                        format!("Invalid base64 request body: {}", e),
+                    ))?;
+                request.body(bytes)
+            }
+            CosmoBody::Graphql { query, variables, operation_name } => {
+                request.body(graphql::encode_operation(query, variables.as_ref(), operation_name.as_deref()))
+            }
+        })
+    }
+
+    /// Whether the body's content type carries parameters the bytes depend on,
+    /// so a user-supplied `Content-Type` header must not replace it.
+    pub fn owns_content_type(&self) -> bool {
+        matches!(self, CosmoBody::Multipart { .. })
+    }
+
+    /// The text a user writes in the body, where `{{variables}}` are
+    /// substituted: content, field values, file paths and GraphQL operations.
+    pub fn texts_mut(&mut self) -> Vec<&mut String> {
+        match self {
+            CosmoBody::Raw { content, .. } => vec![content],
+            CosmoBody::Urlencoded { fields } => fields.iter_mut().flat_map(|f| [&mut f.name, &mut f.value]).collect(),
+            CosmoBody::Multipart { parts } => parts
+                .iter_mut()
+                .flat_map(|part| match part {
+                    CosmoMultipartPart::Text { name, value, .. } => vec![name, value],
+                    CosmoMultipartPart::File { name, path, .. } => vec![name, path],
+                })
+                .collect(),
+            CosmoBody::File { path, .. } => vec![path],
+            CosmoBody::Base64 { .. } => Vec::new(),
+            CosmoBody::Graphql { query, variables, operation_name } => {
+                let mut texts = vec![query];
+                texts.extend(operation_name);
+                if let Some(variables) = variables {
+                    json_strings_mut(variables, &mut texts);
+                }
+                texts
+            }
+        }
+    }
+}
+
+/// Collects every string within a JSON value.
+fn json_strings_mut<'a>(value: &'a mut serde_json::Value, texts: &mut Vec<&'a mut String>) {
+    match value {
+        serde_json::Value::String(text) => texts.push(text),
+        serde_json::Value::Array(items) => items.iter_mut().for_each(|item| json_strings_mut(item, texts)),
+        serde_json::Value::Object(fields) => fields.values_mut().for_each(|field| json_strings_mut(field, texts)),
+        _ => {}
+    }
+}
+
+/// Accepts either a tagged body object or a bare string for raw text.
+pub fn deserialize_body<'de, D>(deserializer: D) -> Result<Option<CosmoBody>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum BodyInput {
+        Text(String),
+        Tagged(CosmoBody),
+    }
+
+    Ok(Option::<BodyInput>::deserialize(deserializer)?.map(|input| match input {
+        BodyInput::Text(content) => content.into(),
+        BodyInput::Tagged(body) => body,
+    }))
+}
+
+/// Builds a multipart form, streaming each file part from disk.
+async fn multipart_form(parts: &[CosmoMultipartPart]) -> Result<Form, CosmoError> {
+    // Names and filenames are escaped here rather than percent-encoded by reqwest
+    let mut form = Form::new().percent_encode_noop();
+    for part in parts {
+        let (name, part) = match part {
+            CosmoMultipartPart::Text { name, value, content_type } => {
+                let part = Part::text(value.clone());
+                (name, with_mime(part, content_type.as_deref())?)
+            }
+            CosmoMultipartPart::File { name, path, filename, content_type } => {
+                let file = open_file(path).await?;
+                let len = file.metadata().await.map_err(|e| file_error(path, e))?.len();
+                let filename = filename.clone().unwrap_or_else(|| {
+                    std::path::Path::new(path)
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default()
+                });
+                let content_type = content_type.clone().unwrap_or_else(|| guess_mime(path));
+                let part = Part::stream_with_length(file, len).file_name(escape_quoted(&filename));
+                (name, with_mime(part, Some(&content_type))?)
+            }
+        };
+        form = form.part(escape_quoted(name), part);
+    }
+    Ok(form)
+}
+
+fn with_mime(part: Part, content_type: Option<&str>) -> Result<Part, CosmoError> {
+    match content_type {
+        Some(content_type) => part.mime_str(content_type).map_err(|e| CosmoError::new(
+            CosmoErrorType::ValidationError,
+            format!("Invalid multipart content type '{}': {}", content_type, e),
+        )),
+        None => Ok(part),
+    }
+}
+
+/// Escapes a name or filename for a quoted `Content-Disposition` parameter
+/// the way browsers do.
+fn escape_quoted(value: &str) -> String {
+    value.replace('"', "%22").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn guess_mime(path: &str) -> String {
+    mime_guess::from_path(path).first_or_octet_stream().to_string()
+}
+
+async fn open_file(path: &str) -> Result<tokio::fs::File, CosmoError> {
+    tokio::fs::File::open(path).await.map_err(|e| file_error(path, e))
+}
+
+fn file_error(path: &str, error: std::io::Error) -> CosmoError {
+    CosmoError::new(
+        CosmoErrorType::ValidationError,
+        format!("Failed to read request body file {}: {}", path, error),
+    )
+}
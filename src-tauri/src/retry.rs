@@ -0,0 +1,211 @@
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::{find_header, CosmoError, CosmoErrorType, CosmoResponse};
+
+/// When and how often a failed request is sent again.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoRetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry; each further retry doubles it
+    pub initial_delay_ms: u64,
+    /// Upper bound for any delay, including one asked for by `Retry-After`
+    pub max_delay_ms: u64,
+    /// Randomize each delay between half and all of its value, so clients
+    /// that failed together don't retry together
+    pub jitter: bool,
+    /// Response statuses that are retried
+    pub retry_on_status: Vec<u16>,
+    /// Failures that are retried
+    pub retry_on_errors: Vec<CosmoErrorType>,
+    /// Wait as long as a `Retry-After` header asks instead of backing off
+    pub respect_retry_after: bool,
+    /// Also retry methods that aren't idempotent, such as POST and PATCH
+    pub retry_non_idempotent: bool,
+}
+
+impl Default for CosmoRetryPolicy {
+    fn default() -> Self {
+        CosmoRetryPolicy {
+            max_attempts: 3,
+            initial_delay_ms: 200,
+            max_delay_ms: 10_000,
+            jitter: true,
+            retry_on_status: vec![408, 429, 502, 503, 504],
+            retry_on_errors: vec![
+                CosmoErrorType::ConnectionRefused,
+                CosmoErrorType::ConnectionReset,
+                CosmoErrorType::TimeoutError,
+            ],
+            respect_retry_after: true,
+            retry_non_idempotent: false,
+        }
+    }
+}
+
+impl CosmoRetryPolicy {
+    /// Backoff before retrying after attempt `attempt` (1-based), without jitter.
+    fn backoff(&self, attempt: u32) -> u64 {
+        let factor = 2u64.saturating_pow(attempt.saturating_sub(1));
+        self.initial_delay_ms.saturating_mul(factor).min(self.max_delay_ms)
+    }
+
+    fn delay(&self, attempt: u32, retry_after_ms: Option<u64>) -> u64 {
+        if let Some(ms) = retry_after_ms.filter(|_| self.respect_retry_after) {
+            return ms.min(self.max_delay_ms);
+        }
+        let backoff = self.backoff(attempt);
+        if self.jitter && backoff > 1 {
+            rand::thread_rng().gen_range(backoff / 2..=backoff)
+        } else {
+            backoff
+        }
+    }
+}
+
+/// One attempt at sending a request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct CosmoAttempt {
+    /// 1 for the first attempt
+    pub attempt: u32,
+    /// Response status, when a response was received
+    pub status: Option<u16>,
+    pub error_type: Option<CosmoErrorType>,
+    pub error: Option<String>,
+    pub duration_ms: u128,
+    /// Wait before the next attempt; `None` for the last one
+    pub delay_ms: Option<u64>,
+}
+
+/// Methods that can be repeated without changing the outcome (RFC 9110).
+fn is_idempotent(method: &str) -> bool {
+    ["GET", "HEAD", "PUT", "DELETE", "OPTIONS", "TRACE"]
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(method.trim()))
+}
+
+/// Parses `Retry-After` as delay-seconds or an HTTP date.
+fn retry_after_ms(value: &str) -> Option<u64> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(seconds.saturating_mul(1000));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let wait = date.signed_duration_since(chrono::Utc::now()).num_milliseconds();
+    Some(wait.max(0) as u64)
+}
+
+/// Runs `attempt` until it succeeds with a status that isn't retried, fails
+/// with an error that isn't retried, or `policy.max_attempts` is used up.
+///
+/// Methods that aren't idempotent get a single attempt unless the policy says
+/// otherwise, and streamed responses are never retried since their body is
+/// already being delivered. The attempts are logged on the returned response,
+/// or on the error when the last attempt failed.
+pub async fn send_with_retries<F, Fut>(policy: &CosmoRetryPolicy, method: &str, mut attempt: F) -> Result<CosmoResponse, CosmoError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<CosmoResponse, CosmoError>>,
+{
+    let max_attempts = if policy.retry_non_idempotent || is_idempotent(method) {
+        policy.max_attempts.max(1)
+    } else {
+        1
+    };
+    let mut attempts = Vec::new();
+
+    loop {
+        let number = attempts.len() as u32 + 1;
+        let started = std::time::Instant::now();
+        let result = attempt().await;
+
+        let retry = number < max_attempts
+            && match &result {
+                Ok(response) => !response.is_stream && policy.retry_on_status.contains(&response.status),
+                Err(error) => policy.retry_on_errors.contains(&error.error_type),
+            };
+        let delay_ms = retry.then(|| {
+            let retry_after = match &result {
+                Ok(response) => find_header(&response.headers, "retry-after").and_then(retry_after_ms),
+                Err(_) => None,
+            };
+            policy.delay(number, retry_after)
+        });
+        attempts.push(CosmoAttempt {
+            attempt: number,
+            status: result.as_ref().ok().map(|response| response.status),
+            error_type: result.as_ref().err().map(|error| error.error_type),
+            error: result.as_ref().err().map(|error| error.message.clone()),
+            duration_ms: started.elapsed().as_millis(),
+            delay_ms,
+        });
+
+        match delay_ms {
+            Some(ms) => tokio::time::sleep(Duration::from_millis(ms)).await,
+            None => {
+                return match result {
+                    Ok(mut response) => {
+                        response.attempts = attempts;
+                        Ok(response)
+                    }
+                    Err(mut error) => {
+                        if attempts.len() > 1 {
+                            error.message = format!("{} (gave up after {} attempts)", error.message, attempts.len());
+                        }
+                        error.attempts = attempts;
+                        Err(error)
+                    }
+                };
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_delays() {
+        let policy = CosmoRetryPolicy { initial_delay_ms: 100, max_delay_ms: 1000, jitter: false, ..Default::default() };
+        let delays: Vec<u64> = (1..=6).map(|attempt| policy.delay(attempt, None)).collect();
+        assert_eq!(delays, vec![100, 200, 400, 800, 1000, 1000]);
+        assert_eq!(policy.backoff(u32::MAX), 1000);
+
+        // Retry-After wins over the backoff, up to the maximum delay
+        assert_eq!(policy.delay(1, Some(700)), 700);
+        assert_eq!(policy.delay(1, Some(5000)), 1000);
+        let ignoring = CosmoRetryPolicy { respect_retry_after: false, ..policy.clone() };
+        assert_eq!(ignoring.delay(1, Some(700)), 100);
+
+        let jittered = CosmoRetryPolicy { jitter: true, ..policy };
+        for _ in 0..20 {
+            assert!((200..=400).contains(&jittered.delay(3, None)));
+        }
+    }
+
+    #[test]
+    fn test_retry_after_and_methods() {
+        assert_eq!(retry_after_ms(" 120 "), Some(120_000));
+        assert_eq!(retry_after_ms("Wed, 21 Oct 2015 07:28:00 GMT"), Some(0));
+        let later = (chrono::Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        assert!((28_000..=30_000).contains(&retry_after_ms(&later).unwrap()));
+        assert_eq!(retry_after_ms("soon"), None);
+
+        assert!(is_idempotent("get") && is_idempotent(" PUT") && is_idempotent("DELETE"));
+        assert!(!is_idempotent("POST") && !is_idempotent("PATCH"));
+    }
+
+    #[test]
+    fn test_policy_defaults() {
+        let policy: CosmoRetryPolicy = serde_json::from_str(r#"{"max_attempts": 5, "retry_on_errors": ["ConnectionReset"]}"#).unwrap();
+        assert_eq!(policy.max_attempts, 5);
+        assert_eq!(policy.retry_on_errors, vec![CosmoErrorType::ConnectionReset]);
+        assert_eq!(policy.retry_on_status, CosmoRetryPolicy::default().retry_on_status);
+    }
+}
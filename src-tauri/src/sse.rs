@@ -0,0 +1,107 @@
+use serde::Serialize;
+
+/// A dispatched Server-Sent Event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SseEvent {
+    /// Last event id seen on the stream, which persists across events
+    pub id: Option<String>,
+    /// Event type; `message` when the event doesn't name one
+    pub event: String,
+    /// Data lines joined with `\n`
+    pub data: String,
+    /// Reconnection time in milliseconds, if set since the previous event
+    pub retry: Option<u64>,
+}
+
+/// Incremental parser for a `text/event-stream` body, following the
+/// WHATWG HTML event stream interpretation rules.
+///
+/// Bytes are buffered until a full line is available, so events and
+/// multi-byte characters may be split across chunks arbitrarily.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    line: Vec<u8>,
+    /// The previous chunk ended with CR, so a leading LF completes that line break
+    skip_lf: bool,
+    /// At least one line has been read, so a BOM is no longer expected
+    started: bool,
+    event_type: String,
+    data: String,
+    has_data: bool,
+    last_event_id: Option<String>,
+    retry: Option<u64>,
+}
+
+impl SseParser {
+    /// Feeds the next chunk of the body, returning the events it completes.
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        let mut events = Vec::new();
+        for &byte in chunk {
+            if std::mem::take(&mut self.skip_lf) && byte == b'\n' {
+                continue;
+            }
+            match byte {
+                b'\r' | b'\n' => {
+                    self.skip_lf = byte == b'\r';
+                    let line = std::mem::take(&mut self.line);
+                    if let Some(event) = self.process_line(&line) {
+                        events.push(event);
+                    }
+                }
+                _ => self.line.push(byte),
+            }
+        }
+        events
+    }
+
+    fn process_line(&mut self, line: &[u8]) -> Option<SseEvent> {
+        let line = String::from_utf8_lossy(line);
+        let line = if std::mem::replace(&mut self.started, true) {
+            &line[..]
+        } else {
+            line.strip_prefix('\u{feff}').unwrap_or(&line)
+        };
+
+        if line.is_empty() {
+            return self.dispatch();
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event_type = value.to_string(),
+            "data" => {
+                if self.has_data {
+                    self.data.push('\n');
+                }
+                self.data.push_str(value);
+                self.has_data = true;
+            }
+            "id" if !value.contains('\0') => self.last_event_id = Some(value.to_string()),
+            "retry" if !value.is_empty() && value.bytes().all(|b| b.is_ascii_digit()) => {
+                self.retry = value.parse().ok();
+            }
+            _ => {}
+        }
+        None
+    }
+
+    fn dispatch(&mut self) -> Option<SseEvent> {
+        let event_type = std::mem::take(&mut self.event_type);
+        let data = std::mem::take(&mut self.data);
+        if !std::mem::take(&mut self.has_data) {
+            return None;
+        }
+
+        Some(SseEvent {
+            id: self.last_event_id.clone(),
+            event: if event_type.is_empty() { "message".to_string() } else { event_type },
+            data,
+            retry: self.retry.take(),
+        })
+    }
+}
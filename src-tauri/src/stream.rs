@@ -0,0 +1,298 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use encoding_rs::{Decoder, Encoding};
+use futures_util::future::{AbortHandle, Abortable};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use tauri::{Emitter, Manager};
+
+use crate::body;
+use crate::compression::BodyDecoder;
+use crate::inflight::InFlightRequests;
+use crate::sse::{SseEvent, SseParser};
+
+/// How a streamed response body is split into messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StreamFraming {
+    /// Server-Sent Events
+    Sse,
+    /// Newline-delimited JSON (or any line-oriented text), one message per line
+    Ndjson,
+    /// Raw chunks as they arrive off the wire
+    Chunks,
+}
+
+/// Streams responses whose content type matches `content_type`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CosmoStreamRule {
+    /// MIME type to match, e.g. `application/x-ndjson`; `text/*` matches a
+    /// whole top-level type
+    pub content_type: String,
+    pub framing: StreamFraming,
+}
+
+impl CosmoStreamRule {
+    fn matches(&self, mime_type: &str) -> bool {
+        let pattern = self.content_type.trim().to_ascii_lowercase();
+        match pattern.strip_suffix("/*") {
+            Some(top_level) => mime_type.split('/').next() == Some(top_level),
+            None => mime_type == pattern,
+        }
+    }
+}
+
+/// Content types streamed without any configuration.
+const BUILT_IN_RULES: [(&str, StreamFraming); 5] = [
+    ("text/event-stream", StreamFraming::Sse),
+    ("application/x-ndjson", StreamFraming::Ndjson),
+    ("application/ndjson", StreamFraming::Ndjson),
+    ("application/jsonl", StreamFraming::Ndjson),
+    ("application/x-jsonlines", StreamFraming::Ndjson),
+];
+
+/// Picks the framing for a response from its content type, trying `rules`
+/// before the built-in ones. Returns `None` when the body should be buffered.
+pub fn framing_for(content_type: Option<&str>, rules: &[CosmoStreamRule]) -> Option<StreamFraming> {
+    let (mime_type, _) = body::parse_content_type(content_type?);
+    let mime_type = mime_type?;
+    rules
+        .iter()
+        .find(|rule| rule.matches(&mime_type))
+        .map(|rule| rule.framing)
+        .or_else(|| {
+            BUILT_IN_RULES
+                .iter()
+                .find(|(built_in, _)| *built_in == mime_type)
+                .map(|(_, framing)| *framing)
+        })
+}
+
+/// Message emitted on a stream's channel.
+///
+/// Every stream starts with `open` and finishes with exactly one of `end` or
+/// `error`; nothing is emitted after that.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamMessage {
+    /// The response headers were received and the body is being read
+    Open { status: u16, framing: StreamFraming },
+    /// A Server-Sent Event
+    Event(SseEvent),
+    /// One line of a line-delimited body, without its line break
+    Line {
+        line: String,
+        /// The line parsed as JSON, when it is valid JSON
+        json: Option<serde_json::Value>,
+    },
+    /// A chunk of the body exactly as received
+    Chunk {
+        /// Offset of the chunk's first byte in the body
+        offset: u64,
+        /// Chunk bytes, base64 encoded
+        data_base64: String,
+        /// The chunk decoded as text, for textual bodies; a character split
+        /// across chunks is included with the chunk that completes it
+        text: Option<String>,
+    },
+    /// The body ended; `closed` is set when the stream was closed or cancelled
+    /// by the client rather than by the server
+    End { closed: bool },
+    /// Reading the body failed
+    Error { message: String },
+}
+
+/// Splits a body into messages as its chunks arrive.
+enum Framer {
+    Sse(SseParser),
+    Lines(LineSplitter),
+    Chunks(ChunkFramer),
+}
+
+impl Framer {
+    fn new(framing: StreamFraming, content_type: Option<&str>) -> Framer {
+        match framing {
+            StreamFraming::Sse => Framer::Sse(SseParser::default()),
+            StreamFraming::Ndjson => Framer::Lines(LineSplitter::default()),
+            StreamFraming::Chunks => Framer::Chunks(ChunkFramer::new(content_type)),
+        }
+    }
+
+    fn feed(&mut self, chunk: &[u8]) -> Vec<StreamMessage> {
+        match self {
+            Framer::Sse(parser) => parser.feed(chunk).into_iter().map(StreamMessage::Event).collect(),
+            Framer::Lines(splitter) => splitter.feed(chunk).into_iter().map(line_message).collect(),
+            Framer::Chunks(framer) => vec![framer.feed(chunk, false)],
+        }
+    }
+
+    /// Flushes anything buffered once the body has ended.
+    fn finish(&mut self) -> Vec<StreamMessage> {
+        match self {
+            // An event without its terminating blank line is discarded
+            Framer::Sse(_) => Vec::new(),
+            Framer::Lines(splitter) => splitter.finish().into_iter().map(line_message).collect(),
+            Framer::Chunks(framer) => {
+                // Only worth sending when an incomplete character was pending
+                let flushed = framer.feed(&[], true);
+                match &flushed {
+                    StreamMessage::Chunk { text: Some(text), .. } if !text.is_empty() => vec![flushed],
+                    _ => Vec::new(),
+                }
+            }
+        }
+    }
+}
+
+fn line_message(line: String) -> StreamMessage {
+    StreamMessage::Line {
+        json: serde_json::from_str(&line).ok(),
+        line,
+    }
+}
+
+/// Splits a body on `\n`, dropping a trailing `\r` and blank lines.
+#[derive(Debug, Default)]
+pub struct LineSplitter {
+    buffer: Vec<u8>,
+}
+
+impl LineSplitter {
+    pub fn feed(&mut self, chunk: &[u8]) -> Vec<String> {
+        let mut lines = Vec::new();
+        for &byte in chunk {
+            if byte == b'\n' {
+                let line = std::mem::take(&mut self.buffer);
+                lines.extend(Self::complete(line));
+            } else {
+                self.buffer.push(byte);
+            }
+        }
+        lines
+    }
+
+    /// Returns the last line when the body didn't end with a line break.
+    pub fn finish(&mut self) -> Option<String> {
+        Self::complete(std::mem::take(&mut self.buffer))
+    }
+
+    fn complete(mut line: Vec<u8>) -> Option<String> {
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return None;
+        }
+        Some(String::from_utf8_lossy(&line).into_owned())
+    }
+}
+
+/// Wraps each chunk with its offset, decoding text incrementally.
+pub struct ChunkFramer {
+    offset: u64,
+    decoder: Option<Decoder>,
+}
+
+impl ChunkFramer {
+    /// `content_type` decides whether chunks carry text, and in which charset.
+    pub fn new(content_type: Option<&str>) -> ChunkFramer {
+        let (mime_type, charset) = content_type.map(body::parse_content_type).unwrap_or((None, None));
+        let textual = mime_type.as_deref().is_some_and(body::is_textual_mime) || charset.is_some();
+        let decoder = textual.then(|| {
+            charset
+                .as_deref()
+                .and_then(|label| Encoding::for_label(label.as_bytes()))
+                .unwrap_or(encoding_rs::UTF_8)
+                .new_decoder()
+        });
+        ChunkFramer { offset: 0, decoder }
+    }
+
+    /// Frames `chunk`; `last` flushes any incomplete character as U+FFFD.
+    pub fn feed(&mut self, chunk: &[u8], last: bool) -> StreamMessage {
+        let text = self.decoder.as_mut().map(|decoder| {
+            let mut text = String::with_capacity(decoder.max_utf8_buffer_length(chunk.len()).unwrap_or(chunk.len() * 3));
+            let _ = decoder.decode_to_string(chunk, &mut text, last);
+            text
+        });
+        let message = StreamMessage::Chunk {
+            offset: self.offset,
+            data_base64: STANDARD.encode(chunk),
+            text,
+        };
+        self.offset += chunk.len() as u64;
+        message
+    }
+}
+
+/// Reads a response body in the background, emitting it on `channel_id` as
+/// messages framed according to `framing`. Chunks go through `decoder` first
+/// when the body's content encoding is being undone.
+///
+/// The task is tracked under `request_id` so it can be closed with
+/// `close_cosmo_stream` or cancelled along with its request.
+pub fn spawn_stream(
+    handle: tauri::AppHandle,
+    response: reqwest::Response,
+    mut decoder: Option<BodyDecoder>,
+    framing: StreamFraming,
+    channel_id: String,
+    request_id: Option<String>,
+) {
+    let (abort_handle, abort_registration) = AbortHandle::new_pair();
+    if let (Some(id), Some(in_flight)) = (&request_id, handle.try_state::<InFlightRequests>()) {
+        in_flight.track_stream(id, &channel_id, abort_handle);
+    }
+
+    tokio::spawn(async move {
+        let emit = |message: StreamMessage| {
+            if let Err(e) = handle.emit(&channel_id, message) {
+                log::error!("Failed to emit on stream {}: {}", channel_id, e);
+            }
+        };
+
+        emit(StreamMessage::Open { status: response.status().as_u16(), framing });
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let mut framer = Framer::new(framing, content_type.as_deref());
+        let forward = async {
+            let mut body = response.bytes_stream();
+            while let Some(chunk) = body.next().await {
+                let bytes = chunk.map_err(|e| e.to_string())?;
+                let bytes = match decoder.as_mut() {
+                    Some(decoder) => decoder.feed(&bytes)?,
+                    None => bytes.to_vec(),
+                };
+                if !bytes.is_empty() {
+                    framer.feed(&bytes).into_iter().for_each(emit);
+                }
+            }
+            if let Some(decoder) = decoder.as_mut() {
+                let rest = decoder.finish()?;
+                if !rest.is_empty() {
+                    framer.feed(&rest).into_iter().for_each(emit);
+                }
+            }
+            framer.finish().into_iter().for_each(emit);
+            Ok(())
+        };
+
+        match Abortable::new(forward, abort_registration).await {
+            Ok(Ok(())) => emit(StreamMessage::End { closed: false }),
+            Ok(Err(message)) => {
+                log::error!("Error reading stream {}: {}", channel_id, message);
+                emit(StreamMessage::Error { message });
+            }
+            Err(_) => {
+                log::info!("Stream {} closed", channel_id);
+                emit(StreamMessage::End { closed: true });
+            }
+        }
+        if let (Some(id), Some(in_flight)) = (&request_id, handle.try_state::<InFlightRequests>()) {
+            in_flight.release_stream(id);
+        }
+    });
+}
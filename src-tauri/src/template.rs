@@ -0,0 +1,407 @@
+use base64::Engine;
+use chrono::{DateTime, SecondsFormat, Utc};
+use hmac::{Hmac, Mac};
+use rand::distributions::Alphanumeric;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use sha2::Digest;
+
+/// A `{{$name(arg, ...)}}` placeholder; the parentheses are optional when
+/// there are no arguments.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HelperCall {
+    pub name: String,
+    pub args: Vec<HelperArg>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HelperArg {
+    /// A quoted string, or a bare number or time offset such as `-1h`
+    Text(String),
+    /// A bare name, standing for the variable's value
+    Variable(String),
+    /// Another helper, whose result is the argument
+    Call(HelperCall),
+}
+
+/// Parses the inside of a `{{$...}}` placeholder.
+pub fn parse(expression: &str) -> Result<HelperCall, String> {
+    let mut parser = Parser { input: expression, pos: 0 };
+    let call = parser.call()?;
+    parser.skip_whitespace();
+    match parser.rest() {
+        "" => Ok(call),
+        rest => Err(format!("unexpected '{}'", rest)),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.pos += expected.len_utf8();
+        }
+        found
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Names, numbers and offsets.
+    fn word(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-' | '+')))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+
+    fn call(&mut self) -> Result<HelperCall, String> {
+        if !self.eat('$') {
+            return Err("helpers start with '$'".to_string());
+        }
+        let name = self.word().to_string();
+        if name.is_empty() {
+            return Err("missing helper name after '$'".to_string());
+        }
+
+        let mut args = Vec::new();
+        self.skip_whitespace();
+        if self.eat('(') {
+            self.skip_whitespace();
+            if !self.eat(')') {
+                loop {
+                    args.push(self.arg()?);
+                    self.skip_whitespace();
+                    if self.eat(')') {
+                        break;
+                    }
+                    if !self.eat(',') {
+                        return Err(format!("expected ',' or ')' in arguments of ${}", name));
+                    }
+                }
+            }
+        }
+        Ok(HelperCall { name, args })
+    }
+
+    fn arg(&mut self) -> Result<HelperArg, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(quote @ ('"' | '\'')) => self.quoted(quote).map(HelperArg::Text),
+            Some('$') => self.call().map(HelperArg::Call),
+            Some(c) if c.is_ascii_digit() || c == '+' || c == '-' => Ok(HelperArg::Text(self.word().to_string())),
+            Some(c) => match self.word() {
+                "" => Err(format!("unexpected '{}' in arguments", c)),
+                name => Ok(HelperArg::Variable(name.to_string())),
+            },
+            None => Err("missing ')'".to_string()),
+        }
+    }
+
+    /// A string in single or double quotes; a backslash escapes the next character.
+    fn quoted(&mut self, quote: char) -> Result<String, String> {
+        self.eat(quote);
+        let mut text = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '\\' => match chars.next() {
+                    Some((_, escaped)) => text.push(escaped),
+                    None => break,
+                },
+                c if c == quote => {
+                    self.pos += i + c.len_utf8();
+                    return Ok(text);
+                }
+                c => text.push(c),
+            }
+        }
+        Err(format!("unterminated string {}{}", quote, text))
+    }
+}
+
+/// Evaluates the built-in `{{$helpers}}`. Random values come from a single
+/// generator per request, so a seed reproduces every one of them.
+pub struct DynamicValues {
+    rng: StdRng,
+}
+
+impl DynamicValues {
+    pub fn new(seed: Option<u64>) -> DynamicValues {
+        DynamicValues {
+            rng: match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            },
+        }
+    }
+
+    /// Runs helper `name` on already evaluated arguments.
+    pub fn call(&mut self, name: &str, args: &[String]) -> Result<String, String> {
+        match name {
+            "uuid" | "guid" | "uuidV4" => {
+                expect_args(name, args, 0, 0)?;
+                Ok(uuid::Builder::from_random_bytes(self.rng.gen()).into_uuid().to_string())
+            }
+            "uuidV7" => {
+                expect_args(name, args, 0, 0)?;
+                let millis = Utc::now().timestamp_millis().max(0) as u64;
+                Ok(uuid::Builder::from_unix_timestamp_millis(millis, &self.rng.gen()).into_uuid().to_string())
+            }
+            "timestamp" => Ok(now_with_offset(name, args)?.timestamp().to_string()),
+            "timestampMs" => Ok(now_with_offset(name, args)?.timestamp_millis().to_string()),
+            "isoTimestamp" => Ok(now_with_offset(name, args)?.to_rfc3339_opts(SecondsFormat::Millis, true)),
+            "randomInt" => {
+                expect_args(name, args, 0, 2)?;
+                let (min, max) = match args {
+                    [] => (0, 1000),
+                    [max] => (0, parse_int(name, max)?),
+                    [min, max, ..] => (parse_int(name, min)?, parse_int(name, max)?),
+                };
+                if min > max {
+                    return Err(format!("${} needs min <= max, got {} and {}", name, min, max));
+                }
+                Ok(self.rng.gen_range(min..=max).to_string())
+            }
+            "randomString" => {
+                expect_args(name, args, 0, 1)?;
+                let len = match args.first() {
+                    Some(len) => parse_int(name, len)?.clamp(0, 4096) as usize,
+                    None => 16,
+                };
+                Ok(self.alphanumeric(len))
+            }
+            "randomEmail" => {
+                expect_args(name, args, 0, 0)?;
+                Ok(format!("user-{}@example.com", self.alphanumeric(10).to_ascii_lowercase()))
+            }
+            "base64" => Ok(base64::engine::general_purpose::STANDARD.encode(single_arg(name, args)?)),
+            "base64Decode" => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(single_arg(name, args)?.trim())
+                    .map_err(|e| format!("${}: {}", name, e))?;
+                String::from_utf8(bytes).map_err(|_| format!("${}: decoded bytes aren't UTF-8 text", name))
+            }
+            "urlEncode" => Ok(urlencoding::encode(single_arg(name, args)?).into_owned()),
+            "urlDecode" => urlencoding::decode(single_arg(name, args)?)
+                .map(|text| text.into_owned())
+                .map_err(|_| format!("${}: decoded bytes aren't UTF-8 text", name)),
+            "sha1" | "sha256" | "sha512" => {
+                expect_args(name, args, 1, 2)?;
+                let input = args[0].as_bytes();
+                let digest = match name {
+                    "sha1" => sha1::Sha1::digest(input).to_vec(),
+                    "sha256" => sha2::Sha256::digest(input).to_vec(),
+                    _ => sha2::Sha512::digest(input).to_vec(),
+                };
+                encode_digest(name, &digest, args.get(1))
+            }
+            "hmacSha1" | "hmacSha256" | "hmacSha512" => {
+                expect_args(name, args, 2, 3)?;
+                let (key, message) = (args[0].as_bytes(), args[1].as_bytes());
+                let mac = match name {
+                    "hmacSha1" => hmac::<Hmac<sha1::Sha1>>(key, message),
+                    "hmacSha256" => hmac::<Hmac<sha2::Sha256>>(key, message),
+                    _ => hmac::<Hmac<sha2::Sha512>>(key, message),
+                };
+                encode_digest(name, &mac, args.get(2))
+            }
+            _ => Err(format!("unknown helper ${}", name)),
+        }
+    }
+
+    fn alphanumeric(&mut self, len: usize) -> String {
+        (&mut self.rng).sample_iter(Alphanumeric).take(len).map(char::from).collect()
+    }
+}
+
+fn expect_args(name: &str, args: &[String], min: usize, max: usize) -> Result<(), String> {
+    if (min..=max).contains(&args.len()) {
+        return Ok(());
+    }
+    let expected = match (min, max) {
+        (0, 0) => "no arguments".to_string(),
+        (min, max) if min == max => format!("{} argument{}", min, if min == 1 { "" } else { "s" }),
+        (min, max) => format!("{} to {} arguments", min, max),
+    };
+    Err(format!("${} takes {}, got {}", name, expected, args.len()))
+}
+
+fn single_arg<'a>(name: &str, args: &'a [String]) -> Result<&'a str, String> {
+    expect_args(name, args, 1, 1)?;
+    Ok(&args[0])
+}
+
+fn parse_int(name: &str, value: &str) -> Result<i64, String> {
+    value
+        .trim()
+        .parse()
+        .map_err(|_| format!("${} expects whole numbers, got '{}'", name, value))
+}
+
+/// The current time, shifted by an optional offset such as `+1h`, `-30m` or `7d`.
+fn now_with_offset(name: &str, args: &[String]) -> Result<DateTime<Utc>, String> {
+    expect_args(name, args, 0, 1)?;
+    let Some(offset) = args.first() else {
+        return Ok(Utc::now());
+    };
+    let offset = offset.trim();
+    let split = offset.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(offset.len());
+    let (amount, unit) = offset.split_at(split);
+    let amount: i64 = amount
+        .trim_start_matches('+')
+        .parse()
+        .map_err(|_| format!("${} expects an offset like +1h or -30m, got '{}'", name, offset))?;
+    let unit_ms: i64 = match unit {
+        "ms" => 1,
+        "" | "s" => 1000,
+        "m" => 60_000,
+        "h" => 3_600_000,
+        "d" => 86_400_000,
+        "w" => 604_800_000,
+        _ => return Err(format!("${}: unknown offset unit '{}', use ms, s, m, h, d or w", name, unit)),
+    };
+    amount
+        .checked_mul(unit_ms)
+        .and_then(chrono::Duration::try_milliseconds)
+        .and_then(|offset| Utc::now().checked_add_signed(offset))
+        .ok_or_else(|| format!("${}: offset '{}' is out of range", name, offset))
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(key: &[u8], message: &[u8]) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(message);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Encodes a digest as `hex` (the default), `base64` or `base64url`.
+fn encode_digest(name: &str, digest: &[u8], encoding: Option<&String>) -> Result<String, String> {
+    match encoding.map(String::as_str).unwrap_or("hex") {
+        "hex" => Ok(hex::encode(digest)),
+        "base64" => Ok(base64::engine::general_purpose::STANDARD.encode(digest)),
+        "base64url" => Ok(base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(digest)),
+        other => Err(format!("${}: unknown encoding '{}', use hex, base64 or base64url", name, other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(value: &str) -> HelperArg {
+        HelperArg::Text(value.to_string())
+    }
+
+    fn call(name: &str, args: &[&str]) -> Result<String, String> {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        DynamicValues::new(None).call(name, &args)
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("$uuid").unwrap(), HelperCall { name: "uuid".to_string(), args: vec![] });
+        assert_eq!(parse("$uuid ( ) ").unwrap().args, vec![]);
+        assert_eq!(
+            parse(r#"$hmacSha256(secret, "a \"b\", c", $sha1('x', 'hex'), -1h, 42)"#).unwrap(),
+            HelperCall {
+                name: "hmacSha256".to_string(),
+                args: vec![
+                    HelperArg::Variable("secret".to_string()),
+                    text(r#"a "b", c"#),
+                    HelperArg::Call(HelperCall { name: "sha1".to_string(), args: vec![text("x"), text("hex")] }),
+                    text("-1h"),
+                    text("42"),
+                ],
+            }
+        );
+
+        assert_eq!(parse("$").unwrap_err(), "missing helper name after '$'");
+        assert_eq!(parse("$base64('x'").unwrap_err(), "expected ',' or ')' in arguments of $base64");
+        assert_eq!(parse("$base64(").unwrap_err(), "missing ')'");
+        assert_eq!(parse("$base64('x)").unwrap_err(), "unterminated string 'x)");
+        assert_eq!(parse("$base64(;)").unwrap_err(), "unexpected ';' in arguments");
+        assert_eq!(parse("$uuid extra").unwrap_err(), "unexpected 'extra'");
+    }
+
+    #[test]
+    fn test_encoding_helpers() {
+        assert_eq!(call("sha256", &["abc"]).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert_eq!(call("sha1", &["abc", "base64"]).unwrap(), "qZk+NkcGgWq6PiVxeFDCbJzQ2J0=");
+        assert_eq!(call("sha1", &["abc", "base64url"]).unwrap(), "qZk-NkcGgWq6PiVxeFDCbJzQ2J0");
+        assert_eq!(
+            call("hmacSha256", &["key", "The quick brown fox jumps over the lazy dog"]).unwrap(),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+        assert_eq!(call("base64", &["hello"]).unwrap(), "aGVsbG8=");
+        assert_eq!(call("base64Decode", &["aGVsbG8="]).unwrap(), "hello");
+        assert_eq!(call("urlEncode", &["a b&c"]).unwrap(), "a%20b%26c");
+        assert_eq!(call("urlDecode", &["a%20b%26c"]).unwrap(), "a b&c");
+
+        assert_eq!(call("sha256", &["abc", "hex32"]).unwrap_err(), "$sha256: unknown encoding 'hex32', use hex, base64 or base64url");
+        assert_eq!(call("base64Decode", &["/w=="]).unwrap_err(), "$base64Decode: decoded bytes aren't UTF-8 text");
+        assert_eq!(call("base64", &[]).unwrap_err(), "$base64 takes 1 argument, got 0");
+        assert_eq!(call("hmacSha1", &["key"]).unwrap_err(), "$hmacSha1 takes 2 to 3 arguments, got 1");
+        assert_eq!(call("nope", &[]).unwrap_err(), "unknown helper $nope");
+    }
+
+    #[test]
+    fn test_time_helpers() {
+        let now = Utc::now().timestamp();
+        let in_an_hour: i64 = call("timestamp", &["+1h"]).unwrap().parse().unwrap();
+        assert!((in_an_hour - now - 3600).abs() <= 2, "{}", in_an_hour - now);
+        let yesterday = call("isoTimestamp", &["-1d"]).unwrap();
+        let yesterday = DateTime::parse_from_rfc3339(&yesterday).unwrap().timestamp();
+        assert!((now - yesterday - 86_400).abs() <= 2);
+        let millis: i64 = call("timestampMs", &[]).unwrap().parse().unwrap();
+        assert!((millis / 1000 - now).abs() <= 2);
+
+        assert_eq!(call("timestamp", &["1y"]).unwrap_err(), "$timestamp: unknown offset unit 'y', use ms, s, m, h, d or w");
+        assert_eq!(call("uuid", &["x"]).unwrap_err(), "$uuid takes no arguments, got 1");
+    }
+
+    #[test]
+    fn test_random_helpers() {
+        let uuid = call("uuid", &[]).unwrap();
+        assert_eq!(uuid::Uuid::parse_str(&uuid).unwrap().get_version_num(), 4);
+        let uuid = call("uuidV7", &[]).unwrap();
+        assert_eq!(uuid::Uuid::parse_str(&uuid).unwrap().get_version_num(), 7);
+
+        // One seed reproduces the whole sequence
+        let sequence = |seed: u64| {
+            let mut values = DynamicValues::new(Some(seed));
+            let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+            vec![
+                values.call("randomInt", &args(&["5", "9"])).unwrap(),
+                values.call("randomString", &args(&["12"])).unwrap(),
+                values.call("randomEmail", &[]).unwrap(),
+                values.call("uuid", &[]).unwrap(),
+            ]
+        };
+        let first = sequence(42);
+        assert_eq!(first, sequence(42));
+        assert_ne!(first, sequence(43));
+        assert!((5..=9).contains(&first[0].parse::<i64>().unwrap()));
+        assert_eq!(first[1].len(), 12);
+        assert!(first[2].ends_with("@example.com"));
+
+        assert_eq!(call("randomInt", &["9", "5"]).unwrap_err(), "$randomInt needs min <= max, got 9 and 5");
+        assert_eq!(call("randomInt", &["x"]).unwrap_err(), "$randomInt expects whole numbers, got 'x'");
+    }
+}
@@ -0,0 +1,280 @@
+use rustls::client::{ClientSessionMemoryCache, ClientSessionStore, Tls12ClientSessionValue, Tls13ClientSessionValue};
+use rustls::pki_types::ServerName;
+use rustls::NamedGroup;
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Instant;
+
+use crate::tls::NegotiatedTls;
+
+/// Milestones of establishing a connection. Only the first connection made
+/// while serving a request is recorded.
+#[derive(Debug, Default, Clone, Copy)]
+struct ConnectionPhases {
+    dns_start: Option<Instant>,
+    dns_end: Option<Instant>,
+    connect_start: Option<Instant>,
+    tls_start: Option<Instant>,
+    connect_end: Option<Instant>,
+}
+
+/// Collects connection milestones for one request.
+///
+/// The trace is made available to the resolver, connector layer and TLS session
+/// store through a task-local while the request is being sent, so each of them
+/// can stamp the phase it owns.
+#[derive(Debug, Default)]
+pub struct ConnectionTrace {
+    phases: Mutex<ConnectionPhases>,
+    /// Outcome of the latest TLS handshake, until taken
+    negotiated_tls: Mutex<Option<NegotiatedTls>>,
+}
+
+tokio::task_local! {
+    static CURRENT_TRACE: Arc<ConnectionTrace>;
+}
+
+impl ConnectionTrace {
+    /// Returns the trace of the request being served by the current task, if any.
+    pub fn current() -> Option<Arc<ConnectionTrace>> {
+        CURRENT_TRACE.try_with(Arc::clone).ok()
+    }
+
+    /// Runs `fut` with this trace installed as the current one.
+    pub async fn scope<F: Future>(self: Arc<Self>, fut: F) -> F::Output {
+        CURRENT_TRACE.scope(self, fut).await
+    }
+
+    /// Whether a new connection was established while this trace was active.
+    pub fn opened_connection(&self) -> bool {
+        self.phases.lock().unwrap().connect_start.is_some()
+    }
+
+    /// Records what a TLS handshake made under this trace negotiated.
+    pub fn record_tls(&self, negotiated: NegotiatedTls) {
+        *self.negotiated_tls.lock().unwrap() = Some(negotiated);
+    }
+
+    /// Takes the outcome of the latest handshake since the last call.
+    pub fn take_negotiated_tls(&self) -> Option<NegotiatedTls> {
+        self.negotiated_tls.lock().unwrap().take()
+    }
+
+    fn record(&self, stamp: impl FnOnce(&mut ConnectionPhases)) {
+        stamp(&mut self.phases.lock().unwrap());
+    }
+}
+
+/// Timing breakdown of a request, in milliseconds.
+///
+/// Connection phases are `None` when no new connection was needed or the phase
+/// does not apply (no DNS lookup for IP literals, no TLS for plain HTTP).
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct CosmoTimings {
+    pub dns_ms: Option<f64>,
+    pub tcp_ms: Option<f64>,
+    pub tls_ms: Option<f64>,
+    /// From the connection being ready until response headers arrived
+    pub ttfb_ms: f64,
+    /// Reading the response body after the headers
+    pub download_ms: f64,
+    /// Wall time of the whole exchange, including the body
+    pub total_ms: f64,
+}
+
+impl CosmoTimings {
+    /// Builds the breakdown from the request's milestones and connection trace.
+    pub fn new(start: Instant, headers_at: Instant, end: Instant, trace: &ConnectionTrace) -> CosmoTimings {
+        let phases = *trace.phases.lock().unwrap();
+        let span = |from: Option<Instant>, to: Option<Instant>| match (from, to) {
+            (Some(from), Some(to)) => Some(millis(from, to)),
+            _ => None,
+        };
+
+        let tcp_start = phases.dns_end.or(phases.connect_start);
+        let tcp_end = phases.tls_start.or(phases.connect_end);
+
+        CosmoTimings {
+            dns_ms: span(phases.dns_start, phases.dns_end),
+            tcp_ms: span(tcp_start, tcp_end),
+            tls_ms: span(phases.tls_start, phases.connect_end),
+            ttfb_ms: millis(phases.connect_end.unwrap_or(start), headers_at),
+            download_ms: millis(headers_at, end),
+            total_ms: millis(start, end),
+        }
+    }
+}
+
+fn millis(from: Instant, to: Instant) -> f64 {
+    to.saturating_duration_since(from).as_secs_f64() * 1000.0
+}
+
+/// DNS resolver that records lookup time on the current trace.
+#[derive(Debug, Default)]
+pub struct TimedResolver;
+
+impl reqwest::dns::Resolve for TimedResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let trace = ConnectionTrace::current();
+        Box::pin(async move {
+            let started = Instant::now();
+            let addrs = tokio::net::lookup_host((name.as_str(), 0)).await.map_err(|source| DnsLookupError {
+                host: name.as_str().to_string(),
+                source,
+            })?;
+            if let Some(trace) = trace {
+                let finished = Instant::now();
+                trace.record(|p| {
+                    if p.dns_start.is_none() {
+                        p.dns_start = Some(started);
+                        p.dns_end = Some(finished);
+                    }
+                });
+            }
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.collect::<Vec<_>>().into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+/// A failed DNS lookup, kept as its own type so the failure can be told
+/// apart from other connection errors.
+#[derive(Debug)]
+pub struct DnsLookupError {
+    host: String,
+    source: std::io::Error,
+}
+
+impl std::fmt::Display for DnsLookupError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "failed to resolve {}", self.host)
+    }
+}
+
+impl std::error::Error for DnsLookupError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+/// Connector layer that records when connection setup starts and finishes.
+///
+/// The inner connector is run with the trace installed so the resolver and TLS
+/// session store can stamp their phases even if hyper finishes the connection
+/// on a background task.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTimingLayer;
+
+impl<S> tower::Layer<S> for ConnectTimingLayer {
+    type Service = ConnectTiming<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ConnectTiming { inner }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConnectTiming<S> {
+    inner: S,
+}
+
+impl<S, R> tower::Service<R> for ConnectTiming<S>
+where
+    S: tower::Service<R>,
+    S::Future: Send + 'static,
+    S::Response: Send + 'static,
+    S::Error: Send + 'static,
+{
+    type Response = S::Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<S::Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, req: R) -> Self::Future {
+        let trace = match ConnectionTrace::current() {
+            Some(trace) => trace,
+            None => return Box::pin(self.inner.call(req)),
+        };
+
+        let started = Instant::now();
+        let first = trace.phases.lock().unwrap().connect_start.is_none();
+        if first {
+            trace.record(|p| p.connect_start = Some(started));
+        }
+
+        let connecting = trace.clone().scope(self.inner.call(req));
+        Box::pin(async move {
+            let result = connecting.await;
+            if first && result.is_ok() {
+                trace.record(|p| p.connect_end = Some(Instant::now()));
+            }
+            result
+        })
+    }
+}
+
+/// TLS session cache that stamps the start of each handshake on the current
+/// trace. rustls consults the store while building the ClientHello, which is
+/// the first thing it does once the TCP connection is up.
+#[derive(Debug)]
+pub struct TracingSessionStore {
+    inner: ClientSessionMemoryCache,
+}
+
+impl Default for TracingSessionStore {
+    fn default() -> Self {
+        TracingSessionStore {
+            inner: ClientSessionMemoryCache::new(256),
+        }
+    }
+}
+
+impl TracingSessionStore {
+    fn mark_handshake_start(&self) {
+        if let Some(trace) = ConnectionTrace::current() {
+            trace.record(|p| {
+                if p.tls_start.is_none() && p.connect_end.is_none() {
+                    p.tls_start = Some(Instant::now());
+                }
+            });
+        }
+    }
+}
+
+impl ClientSessionStore for TracingSessionStore {
+    fn set_kx_hint(&self, server_name: ServerName<'static>, group: NamedGroup) {
+        self.inner.set_kx_hint(server_name, group)
+    }
+
+    fn kx_hint(&self, server_name: &ServerName<'_>) -> Option<NamedGroup> {
+        self.mark_handshake_start();
+        self.inner.kx_hint(server_name)
+    }
+
+    fn set_tls12_session(&self, server_name: ServerName<'static>, value: Tls12ClientSessionValue) {
+        self.inner.set_tls12_session(server_name, value)
+    }
+
+    fn tls12_session(&self, server_name: &ServerName<'_>) -> Option<Tls12ClientSessionValue> {
+        self.inner.tls12_session(server_name)
+    }
+
+    fn remove_tls12_session(&self, server_name: &ServerName<'static>) {
+        self.inner.remove_tls12_session(server_name)
+    }
+
+    fn insert_tls13_ticket(&self, server_name: ServerName<'static>, value: Tls13ClientSessionValue) {
+        self.inner.insert_tls13_ticket(server_name, value)
+    }
+
+    fn take_tls13_ticket(&self, server_name: &ServerName<'static>) -> Option<Tls13ClientSessionValue> {
+        self.mark_handshake_start();
+        self.inner.take_tls13_ticket(server_name)
+    }
+}
@@ -0,0 +1,334 @@
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::crypto::cipher::{AeadKey, Iv, KeyBlockShape, MessageDecrypter, MessageEncrypter, Tls12AeadAlgorithm, Tls13AeadAlgorithm, UnsupportedOperationError};
+use rustls::crypto::{verify_tls12_signature, verify_tls13_signature, CipherSuiteCommon, CryptoProvider};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer, ServerName, UnixTime};
+use rustls::{CipherSuite, ConnectionTrafficSecrets, DigitallySignedStruct, SignatureScheme, SupportedCipherSuite, Tls12CipherSuite, Tls13CipherSuite};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::timings::{ConnectionTrace, TracingSessionStore};
+
+/// TLS settings stored per workspace.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CosmoTlsSettings {
+    /// PEM files with CA certificates trusted in addition to the built-in roots
+    pub ca_certificates: Vec<String>,
+    /// Accept any server certificate without verifying it
+    pub skip_verification: bool,
+    /// Client certificates for mutual TLS, each presented only to its host
+    pub client_certificates: Vec<CosmoClientCertificate>,
+}
+
+/// A client certificate and the host it is presented to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CosmoClientCertificate {
+    /// Host name to match; `*.example.com` matches any subdomain
+    pub host: String,
+    #[serde(flatten)]
+    pub identity: ClientIdentity,
+}
+
+/// Where a client certificate and its private key are loaded from.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "format", rename_all = "lowercase")]
+pub enum ClientIdentity {
+    /// Certificate chain and private key as separate PEM files
+    Pem { cert_path: String, key_path: String },
+    /// PKCS#12 (`.p12` / `.pfx`) bundle holding both
+    Pkcs12 {
+        path: String,
+        #[serde(default)]
+        password: String,
+    },
+}
+
+impl CosmoClientCertificate {
+    fn matches(&self, host: &str) -> bool {
+        let pattern = self.host.trim().to_ascii_lowercase();
+        let host = host.to_ascii_lowercase();
+        match pattern.strip_prefix("*.") {
+            Some(domain) => host.len() > domain.len() && host.ends_with(&format!(".{}", domain)),
+            None => host == pattern,
+        }
+    }
+}
+
+impl CosmoTlsSettings {
+    /// Files the settings read, CA certificates first.
+    pub fn files(&self) -> Vec<&str> {
+        let mut files: Vec<&str> = self.ca_certificates.iter().map(String::as_str).collect();
+        for client_cert in &self.client_certificates {
+            match &client_cert.identity {
+                ClientIdentity::Pem { cert_path, key_path } => files.extend([cert_path.as_str(), key_path.as_str()]),
+                ClientIdentity::Pkcs12 { path, .. } => files.push(path),
+            }
+        }
+        files
+    }
+
+    /// Returns the settings for connecting to `host`, keeping only the first
+    /// client certificate that matches it.
+    pub fn for_host(&self, host: &str) -> CosmoTlsSettings {
+        CosmoTlsSettings {
+            ca_certificates: self.ca_certificates.clone(),
+            skip_verification: self.skip_verification,
+            client_certificates: self
+                .client_certificates
+                .iter()
+                .find(|c| c.matches(host))
+                .cloned()
+                .into_iter()
+                .collect(),
+        }
+    }
+}
+
+/// Builds the rustls configuration used by the request engine.
+///
+/// Mirrors reqwest's built-in rustls setup (ring provider, webpki roots,
+/// TLS 1.2 and 1.3, `h2`/`http/1.1` ALPN) and installs a session store that
+/// records when each handshake starts for the timing breakdown. The cipher
+/// suites record what each handshake negotiated; see [`NegotiatedTls`].
+///
+/// Extra CAs are added to the root store, and the first client certificate in
+/// `settings` is presented whenever a server asks for one; see
+/// [`CosmoTlsSettings::for_host`]. Files are read each time this is called.
+pub fn client_config(settings: &CosmoTlsSettings) -> Result<rustls::ClientConfig, String> {
+    let provider = Arc::new(CryptoProvider {
+        cipher_suites: TRACED_CIPHER_SUITES.clone(),
+        ..rustls::crypto::ring::default_provider()
+    });
+
+    let mut root_store = rustls::RootCertStore::empty();
+    root_store.extend(webpki_roots::TLS_SERVER_ROOTS.iter().cloned());
+    for path in &settings.ca_certificates {
+        for cert in read_pem_certificates(path)? {
+            root_store
+                .add(cert)
+                .map_err(|e| format!("Invalid CA certificate in {}: {}", path, e))?;
+        }
+    }
+
+    let builder = rustls::ClientConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13, &rustls::version::TLS12])
+        .map_err(|e| format!("Invalid TLS configuration: {}", e))?;
+    let builder = if settings.skip_verification {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerification(provider)))
+    } else {
+        builder.with_root_certificates(root_store)
+    };
+    let mut config = match settings.client_certificates.first() {
+        Some(client_cert) => {
+            let (chain, key) = load_identity(&client_cert.identity)?;
+            builder
+                .with_client_auth_cert(chain, key)
+                .map_err(|e| format!("Invalid client certificate for {}: {}", client_cert.host, e))?
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    config.resumption = rustls::client::Resumption::store(Arc::new(TracingSessionStore::default()));
+
+    Ok(config)
+}
+
+fn read_pem_certificates(path: &str) -> Result<Vec<CertificateDer<'static>>, String> {
+    let pem = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Invalid PEM in {}: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("No certificates found in {}", path));
+    }
+    Ok(certs)
+}
+
+fn load_identity(identity: &ClientIdentity) -> Result<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>), String> {
+    match identity {
+        ClientIdentity::Pem { cert_path, key_path } => {
+            let chain = read_pem_certificates(cert_path)?;
+            let key_pem = std::fs::read(key_path).map_err(|e| format!("Failed to read {}: {}", key_path, e))?;
+            let key = PrivateKeyDer::from_pem_slice(&key_pem)
+                .map_err(|e| format!("No usable private key in {}: {}", key_path, e))?;
+            Ok((chain, key))
+        }
+        ClientIdentity::Pkcs12 { path, password } => {
+            let data = std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?;
+            let store = p12_keystore::KeyStore::from_pkcs12(&data, password)
+                .map_err(|e| format!("Failed to open PKCS#12 bundle {}: {}", path, e))?;
+            let (_, key_chain) = store
+                .private_key_chain()
+                .ok_or_else(|| format!("No private key found in {}", path))?;
+            let chain = key_chain
+                .chain()
+                .iter()
+                .map(|cert| CertificateDer::from(cert.as_der().to_vec()))
+                .collect();
+            let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key_chain.key().to_vec()));
+            Ok((chain, key))
+        }
+    }
+}
+
+/// Server certificate verifier used when verification is switched off.
+///
+/// Any certificate is accepted, but handshake signatures are still checked so
+/// the connection is encrypted to whoever holds the presented certificate.
+#[derive(Debug)]
+struct NoVerification(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// Protocol version and cipher suite a TLS handshake settled on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedTls {
+    /// `TLSv1.2` or `TLSv1.3`
+    pub version: &'static str,
+    /// IANA name, e.g. `TLS13_AES_128_GCM_SHA256`
+    pub cipher_suite: String,
+}
+
+lazy_static::lazy_static! {
+    /// The ring provider's cipher suites, each wrapped to report itself on the
+    /// current trace when a handshake installs its keys. Built once, since
+    /// rustls keeps `&'static` references to suites in resumption data.
+    static ref TRACED_CIPHER_SUITES: Vec<SupportedCipherSuite> = rustls::crypto::ring::default_provider()
+        .cipher_suites
+        .into_iter()
+        .map(traced_suite)
+        .collect();
+}
+
+/// Wraps a suite's AEAD algorithm in `TracedAead`.
+///
+/// reqwest keeps the rustls connection inside its private connection type,
+/// and `connector_layer` only sees that type from the outside, so
+/// `ClientConnection::negotiated_cipher_suite()` can't be reached once the
+/// handshake is done. The AEAD algorithm is the one part of a suite rustls
+/// calls into only after choosing it, on the task doing the handshake. The
+/// wrappers are leaked because suites are `&'static`; `TRACED_CIPHER_SUITES`
+/// builds them once per process.
+fn traced_suite(suite: SupportedCipherSuite) -> SupportedCipherSuite {
+    let common = |c: &CipherSuiteCommon| CipherSuiteCommon {
+        suite: c.suite,
+        hash_provider: c.hash_provider,
+        confidentiality_limit: c.confidentiality_limit,
+    };
+    match suite {
+        SupportedCipherSuite::Tls13(s) => SupportedCipherSuite::Tls13(Box::leak(Box::new(Tls13CipherSuite {
+            common: common(&s.common),
+            hkdf_provider: s.hkdf_provider,
+            aead_alg: Box::leak(Box::new(TracedAead { suite: s.common.suite, inner: s.aead_alg })),
+            quic: s.quic,
+        }))),
+        SupportedCipherSuite::Tls12(s) => SupportedCipherSuite::Tls12(Box::leak(Box::new(Tls12CipherSuite {
+            common: common(&s.common),
+            prf_provider: s.prf_provider,
+            kx: s.kx,
+            sign: s.sign,
+            aead_alg: Box::leak(Box::new(TracedAead { suite: s.common.suite, inner: s.aead_alg })),
+        }))),
+    }
+}
+
+/// AEAD algorithm that reports its suite when a handshake creates an
+/// encrypter with it, which happens exactly once the suite is in use.
+struct TracedAead<A: ?Sized + 'static> {
+    suite: CipherSuite,
+    inner: &'static A,
+}
+
+impl<A: ?Sized> TracedAead<A> {
+    fn record(&self, version: &'static str) {
+        if let Some(trace) = ConnectionTrace::current() {
+            trace.record_tls(NegotiatedTls {
+                version,
+                cipher_suite: format!("{:?}", self.suite),
+            });
+        }
+    }
+}
+
+impl Tls13AeadAlgorithm for TracedAead<dyn Tls13AeadAlgorithm> {
+    fn encrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageEncrypter> {
+        self.record("TLSv1.3");
+        self.inner.encrypter(key, iv)
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: Iv) -> Box<dyn MessageDecrypter> {
+        self.inner.decrypter(key, iv)
+    }
+
+    fn key_len(&self) -> usize {
+        self.inner.key_len()
+    }
+
+    fn extract_keys(&self, key: AeadKey, iv: Iv) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        self.inner.extract_keys(key, iv)
+    }
+
+    fn fips(&self) -> bool {
+        self.inner.fips()
+    }
+}
+
+impl Tls12AeadAlgorithm for TracedAead<dyn Tls12AeadAlgorithm> {
+    fn encrypter(&self, key: AeadKey, iv: &[u8], extra: &[u8]) -> Box<dyn MessageEncrypter> {
+        self.record("TLSv1.2");
+        self.inner.encrypter(key, iv, extra)
+    }
+
+    fn decrypter(&self, key: AeadKey, iv: &[u8]) -> Box<dyn MessageDecrypter> {
+        self.inner.decrypter(key, iv)
+    }
+
+    fn key_block_shape(&self) -> KeyBlockShape {
+        self.inner.key_block_shape()
+    }
+
+    fn extract_keys(&self, key: AeadKey, iv: &[u8], explicit: &[u8]) -> Result<ConnectionTrafficSecrets, UnsupportedOperationError> {
+        self.inner.extract_keys(key, iv, explicit)
+    }
+
+    fn fips(&self) -> bool {
+        self.inner.fips()
+    }
+}
@@ -0,0 +1,376 @@
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::{HeaderName, HeaderValue};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
+use tokio_tungstenite::tungstenite::{self, Message};
+use tokio_tungstenite::{Connector, MaybeTlsStream, WebSocketStream};
+use uuid::Uuid;
+
+use crate::tls::{self, CosmoTlsSettings};
+use crate::{CosmoError, CosmoErrorType, CosmoHeader, CosmoTimeouts, WorkspaceContext};
+
+type Socket = WebSocketStream<MaybeTlsStream<tokio::net::TcpStream>>;
+
+/// Bound on connecting and the upgrade handshake when the workspace sets no
+/// `connect_ms`.
+const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 30_000;
+
+/// A WebSocket connection request sent from the frontend.
+#[derive(Debug, Default, Deserialize)]
+pub struct CosmoWebSocketRequest {
+    /// `ws://` or `wss://` URL
+    pub url: String,
+    #[serde(default)]
+    pub headers: Option<HashMap<String, String>>,
+    /// Offered in `Sec-WebSocket-Protocol`, in order of preference
+    #[serde(default)]
+    pub subprotocols: Vec<String>,
+    /// Channel to emit the connection's events on, so the caller can subscribe
+    /// before connecting; generated when absent. Also identifies the
+    /// connection in later commands.
+    #[serde(default)]
+    pub connection_id: Option<String>,
+}
+
+/// Details of an established WebSocket connection.
+#[derive(Debug, Serialize)]
+pub struct CosmoWebSocketOpened {
+    pub connection_id: String,
+    /// Subprotocol selected by the server
+    pub protocol: Option<String>,
+    /// Status of the handshake response, normally 101
+    pub status: u16,
+    /// Headers of the handshake response
+    pub headers: Vec<CosmoHeader>,
+}
+
+/// A frame the frontend asks to send.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CosmoWebSocketFrame {
+    Text { data: String },
+    Binary { data_base64: String },
+    Ping {
+        #[serde(default)]
+        data_base64: String,
+    },
+}
+
+/// Event emitted on a connection's channel.
+///
+/// A connection emits exactly one `close` or `error` as its last event.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum WebSocketEvent {
+    Text { data: String },
+    Binary { data_base64: String },
+    Ping { data_base64: String },
+    Pong { data_base64: String },
+    /// The connection closed; `code` and `reason` come from the close frame,
+    /// which is absent when the connection dropped without one
+    Close {
+        code: Option<u16>,
+        reason: String,
+        /// Whether the close was started by `close_websocket`
+        by_client: bool,
+    },
+    Error { message: String },
+}
+
+/// Instructions passed from commands to a connection's task.
+enum Outgoing {
+    Frame(Message),
+    Close(Option<CloseFrame>),
+}
+
+/// Releases a connection id reserved by `WebSocketSessions::open` when the
+/// connection fails or the open is cancelled, unless committed first.
+struct Reservation<'a> {
+    senders: &'a Mutex<HashMap<String, mpsc::UnboundedSender<Outgoing>>>,
+    connection_id: &'a str,
+    committed: bool,
+}
+
+impl Reservation<'_> {
+    /// Keeps the id reserved for the now open connection.
+    fn commit(mut self) {
+        self.committed = true;
+    }
+}
+
+impl Drop for Reservation<'_> {
+    fn drop(&mut self) {
+        if !self.committed {
+            self.senders.lock().unwrap().remove(self.connection_id);
+        }
+    }
+}
+
+/// Open WebSocket connections keyed by connection id, managed as Tauri state.
+#[derive(Default)]
+pub struct WebSocketSessions {
+    senders: Arc<Mutex<HashMap<String, mpsc::UnboundedSender<Outgoing>>>>,
+}
+
+impl WebSocketSessions {
+    /// Connects to `request.url` and starts forwarding received frames to `emit`
+    /// until the connection closes. `timeouts.connect_ms` bounds connecting
+    /// and the upgrade handshake together.
+    pub async fn open<E>(
+        &self,
+        request: CosmoWebSocketRequest,
+        tls_settings: &CosmoTlsSettings,
+        timeouts: CosmoTimeouts,
+        emit: E,
+    ) -> Result<CosmoWebSocketOpened, CosmoError>
+    where
+        E: Fn(WebSocketEvent) + Send + 'static,
+    {
+        let connection_id = request.connection_id.clone().unwrap_or_else(|| Uuid::new_v4().to_string());
+        // Reserve the id while connecting; frames sent meanwhile are queued
+        let (sender, receiver) = mpsc::unbounded_channel();
+        match self.senders.lock().unwrap().entry(connection_id.clone()) {
+            Entry::Occupied(_) => {
+                return Err(CosmoError::new(
+                    CosmoErrorType::ValidationError,
+                    format!("WebSocket connection '{}' is already open", connection_id),
+                ));
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(sender);
+            }
+        }
+        let reservation = Reservation { senders: &self.senders, connection_id: &connection_id, committed: false };
+
+        let limit_ms = timeouts.connect_ms.unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS);
+        let (socket, response) = tokio::time::timeout(Duration::from_millis(limit_ms), connect(&request, tls_settings))
+            .await
+            .map_err(|_| CosmoError::timeout("connect", Some(limit_ms)))??;
+        reservation.commit();
+        let opened = CosmoWebSocketOpened {
+            connection_id: connection_id.clone(),
+            protocol: response
+                .headers()
+                .get("sec-websocket-protocol")
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            status: response.status().as_u16(),
+            headers: response
+                .headers()
+                .iter()
+                .map(|(name, value)| CosmoHeader::from_pair(name, value))
+                .collect(),
+        };
+
+        let senders = self.senders.clone();
+        tokio::spawn(async move {
+            run_session(socket, receiver, emit).await;
+            senders.lock().unwrap().remove(&connection_id);
+        });
+
+        Ok(opened)
+    }
+
+    /// Queues a frame on an open connection.
+    pub fn send(&self, connection_id: &str, frame: CosmoWebSocketFrame) -> Result<(), String> {
+        let decode = |data: &str| {
+            STANDARD
+                .decode(data.trim())
+                .map_err(|e| format!("Invalid base64 frame payload: {}", e))
+        };
+        let message = match frame {
+            CosmoWebSocketFrame::Text { data } => Message::text(data),
+            CosmoWebSocketFrame::Binary { data_base64 } => Message::binary(decode(&data_base64)?),
+            CosmoWebSocketFrame::Ping { data_base64 } => Message::Ping(decode(&data_base64)?.into()),
+        };
+        self.outgoing(connection_id, Outgoing::Frame(message))
+    }
+
+    /// Starts the closing handshake. Returns `false` if the connection isn't open.
+    pub fn close(&self, connection_id: &str, code: Option<u16>, reason: Option<String>) -> bool {
+        let frame = code.map(|code| CloseFrame {
+            code: CloseCode::from(code),
+            reason: reason.unwrap_or_default().into(),
+        });
+        self.outgoing(connection_id, Outgoing::Close(frame)).is_ok()
+    }
+
+    fn outgoing(&self, connection_id: &str, outgoing: Outgoing) -> Result<(), String> {
+        let senders = self.senders.lock().unwrap();
+        let sender = senders
+            .get(connection_id)
+            .ok_or_else(|| format!("WebSocket connection '{}' is not open", connection_id))?;
+        sender
+            .send(outgoing)
+            .map_err(|_| format!("WebSocket connection '{}' is closing", connection_id))
+    }
+}
+
+async fn connect(request: &CosmoWebSocketRequest, tls_settings: &CosmoTlsSettings) -> Result<(Socket, tungstenite::handshake::client::Response), CosmoError> {
+    let mut handshake = request.url.as_str().into_client_request().map_err(|e| CosmoError::new(
+        CosmoErrorType::InvalidUrl,
+        format!("Invalid WebSocket URL: {}", e),
+    ))?;
+
+    let invalid_header = |name: &str| CosmoError::new(
+        CosmoErrorType::ValidationError,
+        format!("Invalid header '{}'", name),
+    );
+    for (name, value) in request.headers.iter().flatten() {
+        let header_name = HeaderName::from_bytes(name.as_bytes()).map_err(|_| invalid_header(name))?;
+        let header_value = HeaderValue::from_str(value).map_err(|_| invalid_header(name))?;
+        handshake.headers_mut().append(header_name, header_value);
+    }
+    if !request.subprotocols.is_empty() {
+        let protocols = HeaderValue::from_str(&request.subprotocols.join(", "))
+            .map_err(|_| invalid_header("Sec-WebSocket-Protocol"))?;
+        handshake.headers_mut().insert("sec-websocket-protocol", protocols);
+    }
+
+    let host = handshake.uri().host().unwrap_or_default().to_string();
+    let mut tls_config = tls::client_config(&tls_settings.for_host(&host)).map_err(|e| CosmoError::new(
+        CosmoErrorType::SslError,
+        format!("Failed to initialize TLS: {}", e),
+    ))?;
+    // The upgrade handshake is HTTP/1.1 only
+    tls_config.alpn_protocols = vec![b"http/1.1".to_vec()];
+
+    tokio_tungstenite::connect_async_tls_with_config(handshake, None, true, Some(Connector::Rustls(Arc::new(tls_config))))
+        .await
+        .map_err(|e| {
+            let fallback = match &e {
+                tungstenite::Error::Url(_) => CosmoErrorType::InvalidUrl,
+                tungstenite::Error::Tls(_) => CosmoErrorType::SslError,
+                tungstenite::Error::Io(_) => CosmoErrorType::NetworkError,
+                _ => CosmoErrorType::UnknownError,
+            };
+            let message = match &e {
+                tungstenite::Error::Http(response) => {
+                    format!("WebSocket handshake rejected with status {}", response.status())
+                }
+                _ => format!("WebSocket connection failed: {}", e),
+            };
+            CosmoError::caused_by(fallback, message, &e)
+        })
+}
+
+/// Pumps frames both ways until the connection closes.
+async fn run_session<E>(socket: Socket, mut outgoing: mpsc::UnboundedReceiver<Outgoing>, emit: E)
+where
+    E: Fn(WebSocketEvent),
+{
+    let (mut sink, mut incoming) = socket.split();
+    let mut closing = false;
+
+    loop {
+        tokio::select! {
+            received = incoming.next() => match received {
+                Some(Ok(message)) => match message {
+                    Message::Text(text) => emit(WebSocketEvent::Text { data: text.to_string() }),
+                    Message::Binary(data) => emit(WebSocketEvent::Binary { data_base64: STANDARD.encode(&data) }),
+                    Message::Ping(data) => emit(WebSocketEvent::Ping { data_base64: STANDARD.encode(&data) }),
+                    Message::Pong(data) => emit(WebSocketEvent::Pong { data_base64: STANDARD.encode(&data) }),
+                    Message::Close(frame) => {
+                        emit(WebSocketEvent::Close {
+                            code: frame.as_ref().map(|f| u16::from(f.code)),
+                            reason: frame.map(|f| f.reason.to_string()).unwrap_or_default(),
+                            by_client: closing,
+                        });
+                        // Let the library reply to the close frame and finish the handshake
+                        while let Some(Ok(_)) = incoming.next().await {}
+                        return;
+                    }
+                    Message::Frame(_) => {}
+                },
+                Some(Err(e)) => {
+                    emit(WebSocketEvent::Error { message: e.to_string() });
+                    return;
+                }
+                None => {
+                    emit(WebSocketEvent::Close { code: None, reason: String::new(), by_client: closing });
+                    return;
+                }
+            },
+            command = outgoing.recv(), if !closing => {
+                let result = match command {
+                    Some(Outgoing::Frame(message)) => sink.send(message).await,
+                    Some(Outgoing::Close(frame)) => {
+                        closing = true;
+                        sink.send(Message::Close(frame)).await
+                    }
+                    // Every handle to the session was dropped
+                    None => {
+                        closing = true;
+                        sink.send(Message::Close(None)).await
+                    }
+                };
+                if let Err(e) = result {
+                    emit(WebSocketEvent::Error { message: e.to_string() });
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Opens a WebSocket connection, applying the workspace's TLS settings when a
+/// user and workspace are given. Received frames and the close are emitted on
+/// the `connection_id` channel.
+#[tauri::command]
+pub async fn open_websocket(
+    app_handle: tauri::AppHandle,
+    sessions: tauri::State<'_, WebSocketSessions>,
+    request: CosmoWebSocketRequest,
+    user_id: Option<String>,
+    workspace_id: Option<String>,
+) -> Result<CosmoWebSocketOpened, CosmoError> {
+    let (tls_settings, timeouts) = match WorkspaceContext::load(&app_handle, user_id, workspace_id).await {
+        Some(context) => (context.preferences.tls, context.preferences.request_timeouts),
+        None => (CosmoTlsSettings::default(), CosmoTimeouts::default()),
+    };
+
+    let mut request = request;
+    let channel_id = request
+        .connection_id
+        .get_or_insert_with(|| Uuid::new_v4().to_string())
+        .clone();
+    sessions
+        .open(request, &tls_settings, timeouts, move |event| {
+            use tauri::Emitter;
+            if let Err(e) = app_handle.emit(&channel_id, event) {
+                log::error!("Failed to emit on WebSocket {}: {}", channel_id, e);
+            }
+        })
+        .await
+}
+
+/// Sends a text, binary or ping frame on an open connection.
+#[tauri::command]
+pub async fn send_websocket_message(
+    sessions: tauri::State<'_, WebSocketSessions>,
+    connection_id: String,
+    frame: CosmoWebSocketFrame,
+) -> Result<(), String> {
+    sessions.send(&connection_id, frame)
+}
+
+/// Closes a connection, optionally with a close code and reason.
+/// Returns `false` if the connection isn't open.
+#[tauri::command]
+pub async fn close_websocket(
+    sessions: tauri::State<'_, WebSocketSessions>,
+    connection_id: String,
+    code: Option<u16>,
+    reason: Option<String>,
+) -> Result<bool, String> {
+    Ok(sessions.close(&connection_id, code, reason))
+}